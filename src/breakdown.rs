@@ -0,0 +1,203 @@
+use crossterm::event::KeyCode;
+use ratatui::prelude::*;
+use ratatui::widgets::*;
+use std::path::PathBuf;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+
+use crate::fingerprint::{check_artifacts, crate_sizes, scan_units, CrateSize, OTHER_FILES};
+use crate::notify_rw_lock::NotifyRwLock;
+use crate::units::SizeUnit;
+use crate::ProjectTargetAnalysis;
+
+pub(crate) const BREAKDOWN_COMMAND_KEY: KeyCode = KeyCode::Enter;
+const SORT_COMMAND_KEY: char = 's';
+
+/// Order of the crates in the breakdown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BreakdownSort {
+    /// Largest first
+    #[default]
+    Size,
+    /// Alphabetically
+    Name,
+}
+
+/// Where the space of a target directory, together with its build directory, goes
+#[derive(Clone, Debug, Default)]
+pub struct TargetBreakdown {
+    pub crates: Vec<CrateSize>,
+    /// Size of rust-analyzer's own target directory
+    pub rust_analyzer_size: u64,
+    /// Size of the metadata-only builds of `cargo check` and clippy
+    pub check_only_size: u64,
+}
+
+impl TargetBreakdown {
+    /// Analyze the target directory and build directory `dirs`, the target directory first
+    pub fn analyze(dirs: &[PathBuf]) -> Self {
+        let units = dirs
+            .iter()
+            .flat_map(|it| scan_units(it))
+            .collect::<Vec<_>>();
+        let check = check_artifacts(&dirs[0], &units);
+        Self {
+            crates: crate_sizes(dirs, &units),
+            rust_analyzer_size: check.rust_analyzer_size(),
+            check_only_size: check.check_only_size(),
+        }
+    }
+}
+
+/// Drill-down into the target directory of one project, showing the space per crate
+pub struct BreakdownView {
+    pub project_path: PathBuf,
+    pub target_path: PathBuf,
+    /// `None` while the target directory is analyzed
+    pub items: Arc<NotifyRwLock<Option<TargetBreakdown>>>,
+    pub table_state: TableState,
+    pub sort: BreakdownSort,
+}
+
+impl BreakdownView {
+    /// Open the breakdown of `project` and analyze its target directory in the background
+    pub fn open(project: &ProjectTargetAnalysis, notify_tx: SyncSender<()>) -> Self {
+        let items = Arc::new(NotifyRwLock::new(notify_tx, None));
+        let target_path = project.target_path.clone();
+        {
+            let (items, dirs) = (items.clone(), project.artifact_dirs());
+            std::thread::spawn(move || {
+                let breakdown = TargetBreakdown::analyze(&dirs);
+                *items.write() = Some(breakdown);
+            });
+        }
+        Self {
+            project_path: project.project_path.clone(),
+            target_path,
+            items,
+            table_state: TableState::default().with_selected(Some(0)),
+            sort: BreakdownSort::default(),
+        }
+    }
+
+    /// Crates of the target directory in the chosen order, empty while analyzing
+    pub fn sorted_items(&self) -> Vec<CrateSize> {
+        let mut items = self
+            .items
+            .read()
+            .as_ref()
+            .map(|it| it.crates.clone())
+            .unwrap_or_default();
+        if self.sort == BreakdownSort::Name {
+            items.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        items
+    }
+
+    /// Handle a key, returning `false` when the view is closed
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        let len = self.items.read().as_ref().map_or(0, |it| it.crates.len());
+        let selected = self.table_state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Esc | KeyCode::Backspace | BREAKDOWN_COMMAND_KEY => return false,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.table_state
+                    .select(Some((selected + 1).min(len.saturating_sub(1))));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.table_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Char('g') => self.table_state.select(Some(0)),
+            KeyCode::Char('G') => self.table_state.select(Some(len.saturating_sub(1))),
+            KeyCode::Char(SORT_COMMAND_KEY) => {
+                self.sort = match self.sort {
+                    BreakdownSort::Size => BreakdownSort::Name,
+                    BreakdownSort::Name => BreakdownSort::Size,
+                };
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+/// Draw `view` into the table and status areas of the main layout
+pub fn render(f: &mut Frame, view: &mut BreakdownView, rects: [Rect; 2], size_unit: SizeUnit) {
+    let [table_rect, status_rect] = rects;
+    let (analyzed, rust_analyzer_size, check_only_size) = match &*view.items.read() {
+        Some(it) => (true, it.rust_analyzer_size, it.check_only_size),
+        None => (false, 0, 0),
+    };
+    let items = view.sorted_items();
+
+    let yellow = Style::default().fg(Color::Yellow);
+    let size_header = match size_unit {
+        SizeUnit::Auto => "Size".to_string(),
+        unit => format!("Size({})", unit.label()),
+    };
+    let sorted = |title: &str, sort: BreakdownSort| {
+        if view.sort == sort {
+            format!("{title} ▼")
+        } else {
+            title.to_string()
+        }
+    };
+    let header = Row::new([
+        Cell::from(sorted("Crate", BreakdownSort::Name)).style(yellow),
+        Cell::from(sorted(&size_header, BreakdownSort::Size)).style(yellow),
+        Cell::from("Units").style(yellow),
+        Cell::from("Build Scripts").style(yellow),
+        Cell::from("Incremental").style(yellow),
+        Cell::from("Check Only").style(yellow),
+    ]);
+    let rows = items.iter().map(|item| {
+        Row::new([
+            Cell::from(item.name.clone()),
+            Cell::from(size_unit.format(item.size)),
+            Cell::from(item.units.to_string()),
+            Cell::from(size_unit.format(item.build_script_size)),
+            Cell::from(size_unit.format(item.incremental_size)),
+            Cell::from(size_unit.format(item.check_size)),
+        ])
+        .style(Style::default().fg(Color::Green))
+    });
+    let title = format!(
+        "{} {}",
+        view.target_path.display(),
+        if analyzed { "" } else { "(analyzing...)" }
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(40),
+            Constraint::Max(12),
+            Constraint::Max(6),
+            Constraint::Max(14),
+            Constraint::Max(12),
+            Constraint::Max(12),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(Style::default().fg(Color::White).bg(Color::Green))
+    .highlight_symbol(">> ");
+    f.render_stateful_widget(table, table_rect, &mut view.table_state);
+
+    let total = items.iter().map(|it| it.size).sum::<u64>();
+    let status = format!(
+        "{}: {} in {} crates, rust-analyzer: {}, Check only: {}, Sort: {} ({SORT_COMMAND_KEY}), Back (Esc)",
+        view.project_path.display(),
+        size_unit.format(total),
+        items.iter().filter(|it| it.name != OTHER_FILES).count(),
+        size_unit.format(rust_analyzer_size),
+        size_unit.format(check_only_size),
+        match view.sort {
+            BreakdownSort::Size => "size",
+            BreakdownSort::Name => "name",
+        }
+    );
+    f.render_widget(
+        Paragraph::new(Span::styled(status, Style::default().fg(Color::Green))),
+        status_rect,
+    );
+}
@@ -0,0 +1,451 @@
+use crossterm::event::KeyCode;
+use itertools::Itertools;
+use ratatui::prelude::*;
+use ratatui::widgets::*;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::cargo_home::{CacheEntry, CacheKind, CacheRemoval};
+use crate::journal::{self, JournalEntry};
+use crate::notify_rw_lock::NotifyRwLock;
+use crate::tui_app::{
+    centered_rect, delete_progress_text, percent, progress_percent, progress_text, CursorMode,
+    DeleteProgress, DeleteState, DELETE_COMMAND_KEY,
+};
+use crate::units::{format_timestamp, SizeUnit};
+use crate::Progress;
+
+const REMOVAL_COMMAND_KEY: char = 'c';
+const UNUSED_COMMAND_KEY: char = 'u';
+
+fn header(kind: CacheKind) -> [&'static str; 3] {
+    match kind {
+        CacheKind::Registry => ["Registry", "Crate", "Version"],
+        CacheKind::Git => ["Directory", "Repository", "Revision"],
+        CacheKind::Binaries => ["Binaries", "Package", "Version"],
+        CacheKind::Toolchains => ["Host", "Channel", "Date"],
+    }
+}
+
+/// A tab listing the entries of one part of cargo's or rustup's home, selected and deleted like projects
+pub struct CacheTab {
+    pub kind: CacheKind,
+    /// Directory the cache is located in, see [`CacheKind::home`]
+    pub home: PathBuf,
+    pub table_state: TableState,
+    pub items: Arc<NotifyRwLock<Vec<CacheEntry>>>,
+    pub selected_items: HashSet<Uuid>,
+    /// `None` until the tab is shown for the first time
+    pub scan_progress: Option<Arc<NotifyRwLock<Progress>>>,
+    pub delete_state: Option<DeleteState>,
+    pub mode: CursorMode,
+    pub removal: CacheRemoval,
+    /// Whether the entries were compared against the lockfiles of the scanned projects
+    pub usage_marked: bool,
+    /// Unused entries are only selected by `u` when they were not used for this long
+    pub older_than: Duration,
+    pub notify_tx: SyncSender<()>,
+}
+
+impl CacheTab {
+    pub fn new(kind: CacheKind, home: PathBuf, notify_tx: SyncSender<()>) -> Self {
+        Self {
+            kind,
+            home,
+            table_state: TableState::default(),
+            items: Arc::new(NotifyRwLock::new(notify_tx.clone(), vec![])),
+            selected_items: HashSet::new(),
+            scan_progress: None,
+            delete_state: None,
+            mode: CursorMode::Normal,
+            removal: CacheRemoval::default(),
+            usage_marked: false,
+            older_than: Duration::ZERO,
+            notify_tx,
+        }
+    }
+
+    /// Analyze the cache in the background unless that already happened
+    pub fn start_scan(&mut self) {
+        if self.scan_progress.is_some() {
+            return;
+        }
+        let progress = Arc::new(NotifyRwLock::new(
+            self.notify_tx.clone(),
+            Progress {
+                total: 1,
+                scanned: 0,
+            },
+        ));
+        self.scan_progress = Some(Arc::clone(&progress));
+        let (kind, home, items) = (self.kind, self.home.clone(), self.items.clone());
+        std::thread::spawn(move || {
+            let entries = kind.scan(&home);
+            *items.write() = entries;
+            progress.write().scanned = 1;
+        });
+    }
+
+    pub fn is_scanned(&self) -> bool {
+        self.scan_progress.as_ref().is_some_and(|it| {
+            let progress = it.read();
+            progress.scanned == progress.total
+        })
+    }
+
+    /// Replace the selection with the entries no project uses that are older than `older_than`
+    fn select_unused(&mut self) {
+        let now = SystemTime::now();
+        self.selected_items = self
+            .items
+            .read()
+            .iter()
+            .filter(|it| it.is_unused(self.older_than, now))
+            .map(|it| it.id)
+            .collect();
+    }
+
+    fn next(&mut self) {
+        let len = self.items.read().len();
+        let i = match self.table_state.selected() {
+            Some(i) => (i + 1).min(len.saturating_sub(1)),
+            None => 0,
+        };
+        self.table_state.select(Some(i));
+    }
+
+    fn previous(&mut self) {
+        let i = self.table_state.selected().unwrap_or(0).saturating_sub(1);
+        self.table_state.select(Some(i));
+    }
+
+    /// Id of the entry under the cursor, unless it is protected from removal
+    fn selectable_id(&self) -> Option<Uuid> {
+        let selected = self.table_state.selected()?;
+        let items = self.items.read();
+        let item = items.get(selected)?;
+        (!item.protected).then_some(item.id)
+    }
+
+    fn after_move(&mut self) {
+        let Some(selected_id) = self.selectable_id() else {
+            return;
+        };
+        match self.mode {
+            CursorMode::Normal => {}
+            CursorMode::Select => {
+                self.selected_items.insert(selected_id);
+            }
+            CursorMode::Unselect => {
+                self.selected_items.remove(&selected_id);
+            }
+        }
+    }
+
+    /// Handle keys with the same bindings as the project list
+    pub fn handle_key(&mut self, key: KeyCode, dry_run: bool) {
+        match key {
+            KeyCode::Char(DELETE_COMMAND_KEY) => match &self.delete_state {
+                Some(DeleteState::Confirm) => {}
+                Some(DeleteState::Deleting(delete_progress)) => {
+                    let finished = {
+                        let progress = delete_progress.read();
+                        progress.scanned == progress.total
+                    };
+                    if finished {
+                        let removal = self.removal;
+                        let mut items = self.items.write();
+                        for item in items
+                            .iter_mut()
+                            .filter(|it| self.selected_items.contains(&it.id))
+                        {
+                            item.parts.retain(|it| !removal.includes(it.kind));
+                        }
+                        items.retain(|it| !it.parts.is_empty());
+                        drop(items);
+                        self.selected_items.clear();
+                        self.delete_state = None;
+                    }
+                }
+                None => {
+                    if !self.selected_items.is_empty() {
+                        self.delete_state = Some(DeleteState::Confirm);
+                    }
+                }
+            },
+            KeyCode::Char('Y') => {
+                if let Some(DeleteState::Confirm) = self.delete_state {
+                    let remove_targets = self
+                        .items
+                        .read()
+                        .iter()
+                        .filter(|it| self.selected_items.contains(&it.id))
+                        .cloned()
+                        .collect_vec();
+                    let delete_progress = Arc::new(NotifyRwLock::new(
+                        self.notify_tx.clone(),
+                        DeleteProgress::new(remove_targets.len()),
+                    ));
+                    self.delete_state = Some(DeleteState::Deleting(delete_progress.clone()));
+                    let (kind, removal) = (self.kind, self.removal);
+                    let home = self.home.clone();
+                    std::thread::spawn(move || {
+                        for target in remove_targets {
+                            if !dry_run {
+                                let result = kind.remove(&home, &target, removal);
+                                let journaled = journal::append(&JournalEntry::new(
+                                    "tui",
+                                    target.path(removal),
+                                    &kind.describe(removal),
+                                    &result,
+                                ));
+                                if result.is_err() || journaled.is_err() {
+                                    delete_progress.write().failed += 1;
+                                }
+                            }
+                            delete_progress.write().scanned += 1;
+                        }
+                    });
+                }
+            }
+            KeyCode::Char('n') => {
+                if let Some(DeleteState::Confirm) = self.delete_state {
+                    self.delete_state = None;
+                }
+            }
+            KeyCode::Char(REMOVAL_COMMAND_KEY)
+                if self.delete_state.is_none() && self.kind.has_removal_choice() =>
+            {
+                self.removal = self.removal.next();
+            }
+            KeyCode::Char(UNUSED_COMMAND_KEY) if self.delete_state.is_none() => {
+                self.select_unused();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.next();
+                self.after_move();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.previous();
+                self.after_move();
+            }
+            KeyCode::Char('g') => {
+                self.table_state.select(Some(0));
+                self.after_move();
+            }
+            KeyCode::Char('G') => {
+                let len = self.items.read().len();
+                self.table_state.select(Some(len.saturating_sub(1)));
+                self.after_move();
+            }
+            KeyCode::Char(' ') => {
+                if let Some(id) = self.selectable_id() {
+                    if !self.selected_items.remove(&id) {
+                        self.selected_items.insert(id);
+                    }
+                }
+            }
+            KeyCode::Char('v') => {
+                self.mode = CursorMode::Select;
+                self.after_move();
+            }
+            KeyCode::Char('V') => {
+                self.mode = CursorMode::Unselect;
+                self.after_move();
+            }
+            KeyCode::Esc => self.mode = CursorMode::Normal,
+            _ => {}
+        }
+    }
+}
+
+/// Draw `tab` into the progress, table and status areas of the main layout
+pub fn render(
+    f: &mut Frame,
+    tab: &mut CacheTab,
+    rects: [Rect; 3],
+    size_unit: SizeUnit,
+    dry_run: bool,
+) {
+    let [progress_rect, table_rect, status_rect] = rects;
+
+    {
+        let progress = match &tab.scan_progress {
+            Some(progress) => {
+                let progress = progress.read();
+                Progress {
+                    total: progress.total,
+                    scanned: progress.scanned,
+                }
+            }
+            None => Progress {
+                total: 1,
+                scanned: 0,
+            },
+        };
+        let gauge = Gauge::default()
+            .gauge_style(Style::new().light_green().on_gray())
+            .percent(progress_percent(&progress))
+            .label(Span::styled(
+                progress_text(&progress),
+                Style::default().fg(Color::Black),
+            ));
+        f.render_widget(gauge, progress_rect);
+    }
+
+    let removal = tab.removal;
+    {
+        let yellow = Style::default().fg(Color::Yellow);
+        let [group, name, version] = header(tab.kind);
+        let size_header = match size_unit {
+            SizeUnit::Auto => "Size".to_string(),
+            unit => format!("Size({})", unit.label()),
+        };
+        let header = Row::new([
+            Cell::from(group).style(yellow),
+            Cell::from(name).style(yellow),
+            Cell::from(version).style(yellow),
+            Cell::from(size_header).style(yellow),
+            Cell::from("Last Used").style(yellow),
+            Cell::from("In Use").style(yellow),
+        ]);
+        let items = tab.items.read();
+        let rows = items.iter().map(|item| {
+            let row = Row::new([
+                Cell::from(item.group.as_str()),
+                Cell::from(item.name.as_str()),
+                Cell::from(item.version.as_str()),
+                Cell::from(size_unit.format(item.size(removal))),
+                Cell::from(format_timestamp(item.last_used)[..10].to_string()),
+                Cell::from(match item.in_use {
+                    _ if item.protected => "kept",
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "",
+                }),
+            ]);
+            if tab.selected_items.contains(&item.id) {
+                row.style(Style::default().fg(Color::Blue).bg(Color::Yellow))
+            } else if item.protected {
+                row.style(Style::default().fg(Color::DarkGray))
+            } else {
+                row.style(Style::default().fg(Color::Green))
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(35),
+                Constraint::Percentage(30),
+                Constraint::Max(16),
+                Constraint::Max(12),
+                Constraint::Max(10),
+                Constraint::Max(6),
+            ],
+        )
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Cargo Cleaner - {} {}",
+            tab.home.display(),
+            if dry_run { "(dry-run)" } else { "" }
+        )))
+        .row_highlight_style(Style::default().fg(Color::White).bg(Color::Green))
+        .highlight_symbol(">> ");
+        f.render_stateful_widget(table, table_rect, &mut tab.table_state);
+    }
+
+    {
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(50),
+                Constraint::Length(9),
+                Constraint::Length(10),
+            ])
+            .split(status_rect);
+        let items = tab.items.read();
+        let total = items.iter().map(|it| it.size(removal)).sum::<u64>();
+        let selected = items
+            .iter()
+            .filter(|it| tab.selected_items.contains(&it.id))
+            .map(|it| it.size(removal))
+            .sum::<u64>();
+        let mut status_text = format!(
+            "Total: {}, Selected: {}",
+            size_unit.format(total),
+            size_unit.format(selected)
+        );
+        if tab.usage_marked {
+            let now = SystemTime::now();
+            let unused = items
+                .iter()
+                .filter(|it| it.is_unused(tab.older_than, now))
+                .map(|it| it.size(removal))
+                .sum::<u64>();
+            status_text.push_str(&format!(
+                ", Unused: {} ({UNUSED_COMMAND_KEY})",
+                size_unit.format(unused)
+            ));
+        }
+        if tab.kind.has_removal_choice() {
+            status_text.push_str(&format!(
+                ", Removes: {} ({REMOVAL_COMMAND_KEY})",
+                removal.as_str()
+            ));
+        }
+        f.render_widget(
+            Paragraph::new(Span::styled(status_text, Style::default().fg(Color::Green))),
+            rects[0],
+        );
+        f.render_widget(
+            Paragraph::new(Span::styled("h: help", Style::default().fg(Color::Green))),
+            rects[1],
+        );
+        let mode_text = match tab.mode {
+            CursorMode::Normal => "Normal",
+            CursorMode::Select => "Select",
+            CursorMode::Unselect => "Unselect",
+        };
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                mode_text,
+                Style::default().fg(Color::White).bg(Color::Blue),
+            ))
+            .alignment(Alignment::Right),
+            rects[2],
+        );
+    }
+
+    if let Some(delete_state) = &tab.delete_state {
+        let area = centered_rect(60, 30, f.area());
+        f.render_widget(Clear, area);
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL))
+            .gauge_style(Style::new().light_blue().on_black())
+            .red();
+        let gauge = match delete_state {
+            DeleteState::Confirm => gauge.percent(0).label(Span::styled(
+                format!(
+                    "Are you sure you want to remove {} entries ({})? (Y/n)",
+                    tab.selected_items.len(),
+                    tab.kind.describe(removal)
+                ),
+                Style::default().fg(Color::Yellow),
+            )),
+            DeleteState::Deleting(progress) => {
+                let progress = progress.read();
+                gauge
+                    .percent(percent(progress.scanned, progress.total))
+                    .label(Span::styled(
+                        delete_progress_text(&progress, dry_run),
+                        Style::default().fg(Color::Yellow),
+                    ))
+            }
+        };
+        f.render_widget(gauge, area);
+    }
+}
@@ -0,0 +1,135 @@
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+use rustc_stable_hash::StableSipHasher128;
+
+use crate::cargo_home::cargo_home;
+
+/// Resolve the target directory of the project at `project_path`.
+///
+/// Honors `build.target-dir` from `.cargo/config.toml` (or the legacy `.cargo/config`) in the
+/// project directory and its ancestors, the nearest definition wins like in cargo. Relative
+/// values are resolved against the directory containing `.cargo`.
+pub fn resolve_target_dir(project_path: &Path) -> PathBuf {
+    find_build_setting(project_path, "target-dir")
+        .map(|(dir, setting)| normalize(&dir.join(setting)))
+        .unwrap_or_else(|| project_path.join("target"))
+}
+
+/// Resolve the directory cargo keeps intermediate build artifacts in when `build.build-dir` sets
+/// it apart from the target directory, `None` when they are the same.
+///
+/// Members of a workspace build into the directory of the workspace, so it is only attributed to
+/// the workspace root and `None` for the members. The templates `{workspace-root}`,
+/// `{cargo-cache-home}` and `{workspace-path-hash}` are expanded like cargo does.
+pub fn resolve_build_dir(project_path: &Path) -> Option<PathBuf> {
+    let (dir, setting) = find_build_setting(project_path, "build-dir")?;
+    let workspace_root = workspace_root(project_path);
+    if workspace_root != project_path {
+        return None;
+    }
+    let mut setting = setting.replace("{workspace-root}", &workspace_root.to_string_lossy());
+    if setting.contains("{cargo-cache-home}") {
+        let cargo_home = cargo_home()?;
+        setting = setting.replace("{cargo-cache-home}", &cargo_home.to_string_lossy());
+    }
+    if setting.contains("{workspace-path-hash}") {
+        let manifest = workspace_root.join("Cargo.toml");
+        let manifest = manifest.canonicalize().unwrap_or(manifest);
+        setting = setting.replace("{workspace-path-hash}", &workspace_path_hash(&manifest));
+    }
+    Some(normalize(&dir.join(setting))).filter(|it| *it != resolve_target_dir(project_path))
+}
+
+/// Resolve `.` and `..` in `path` without touching the disk, so a directory configured relative
+/// to another one compares equal to where it is found while scanning
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The directory of the workspace `project_path` belongs to: the nearest ancestor (or the
+/// project itself) whose manifest has a `[workspace]` table, else the project
+fn workspace_root(project_path: &Path) -> PathBuf {
+    project_path
+        .ancestors()
+        .find(|dir| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .ok()
+                .and_then(|text| text.parse::<toml::Table>().ok())
+                .is_some_and(|manifest| manifest.contains_key("workspace"))
+        })
+        .unwrap_or(project_path)
+        .to_owned()
+}
+
+/// `{workspace-path-hash}` of the workspace manifest at `manifest_path`: the stable hash cargo
+/// uses for it, as hex split into a two character directory and the rest
+fn workspace_path_hash(manifest_path: &Path) -> String {
+    let mut hasher = StableSipHasher128::new();
+    manifest_path.hash(&mut hasher);
+    let hash = Hasher::finish(&hasher)
+        .to_le_bytes()
+        .iter()
+        .map(|it| format!("{it:02x}"))
+        .collect::<String>();
+    format!("{}{MAIN_SEPARATOR}{}", &hash[..2], &hash[2..])
+}
+
+/// The nearest definition of `[build].<key>` with the directory containing its `.cargo`
+fn find_build_setting(project_path: &Path, key: &str) -> Option<(PathBuf, String)> {
+    project_path.ancestors().find_map(|dir| {
+        ["config.toml", "config"].iter().find_map(|file_name| {
+            let text = std::fs::read_to_string(dir.join(".cargo").join(file_name)).ok()?;
+            let value = text.parse::<toml::Table>().ok()?;
+            let setting = value.get("build")?.get(key)?.as_str()?;
+            Some((dir.to_owned(), setting.to_string()))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn workspace_path_hash_matches_cargo() {
+        // The directory cargo created for `build-dir = "/tmp/bd/{workspace-path-hash}"`
+        let hash = workspace_path_hash(Path::new("/tmp/stampdemo/Cargo.toml"));
+        assert_eq!(hash, format!("2f{MAIN_SEPARATOR}3b3181bffea9bc"));
+    }
+
+    #[test]
+    fn build_dir_templates_are_expanded() {
+        let root = TempDir::new("build-dir");
+        let project = root.join("member");
+        std::fs::create_dir_all(root.join(".cargo")).unwrap();
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"member\"\n").unwrap();
+        std::fs::write(
+            root.join(".cargo/config.toml"),
+            "[build]\nbuild-dir = \"{workspace-root}/build\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(resolve_build_dir(&root), Some(root.join("build")));
+        assert_eq!(resolve_build_dir(&project), None);
+        assert_eq!(resolve_target_dir(&project), project.join("target"));
+    }
+}
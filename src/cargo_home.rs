@@ -0,0 +1,332 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use dirs::home_dir;
+use uuid::Uuid;
+
+use crate::git_cache;
+use crate::installed;
+use crate::lockfile::{collect_locked_packages, LockedPackage};
+use crate::registry;
+use crate::toolchain::{self, collect_toolchain_pins, rustup_home, Toolchain};
+
+/// Location of cargo's home, `$CARGO_HOME` falling back to `~/.cargo`
+pub fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .filter(|it| it.is_absolute())
+        .or_else(|| home_dir().map(|it| it.join(".cargo")))
+}
+
+/// What the scanned projects need from the caches
+#[derive(Clone, Debug, Default)]
+pub struct ProjectReferences {
+    /// Packages locked by any `Cargo.lock`
+    pub packages: HashSet<LockedPackage>,
+    /// Toolchains pinned by the projects or by rustup's settings
+    pub toolchains: HashSet<Toolchain>,
+}
+
+impl ProjectReferences {
+    /// Read the lockfiles and toolchain pins of the projects at `project_paths`
+    pub fn collect<'a>(
+        project_paths: impl IntoIterator<Item = &'a Path>,
+    ) -> (Self, Vec<anyhow::Error>) {
+        let project_paths = project_paths.into_iter().collect::<Vec<_>>();
+        let (packages, mut errors) = collect_locked_packages(project_paths.iter().copied());
+        let (toolchains, toolchain_errors) =
+            collect_toolchain_pins(project_paths, rustup_home().as_deref());
+        errors.extend(toolchain_errors);
+        (
+            Self {
+                packages,
+                toolchains,
+            },
+            errors,
+        )
+    }
+}
+
+/// Part of cargo's (or rustup's) home analyzed as a cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CacheKind {
+    /// `registry/{index,cache,src}`
+    Registry,
+    /// `git/{db,checkouts}`
+    Git,
+    /// Binaries of packages installed by `cargo install`, tracked in `.crates2.json`
+    Binaries,
+    /// `toolchains` of rustup's home
+    Toolchains,
+}
+
+impl CacheKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            CacheKind::Registry => "Registry",
+            CacheKind::Git => "Git",
+            CacheKind::Binaries => "Binaries",
+            CacheKind::Toolchains => "Toolchains",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheKind::Registry => "registry",
+            CacheKind::Git => "git",
+            CacheKind::Binaries => "binaries",
+            CacheKind::Toolchains => "toolchains",
+        }
+    }
+
+    /// Whether the user chooses which parts of an entry are removed
+    pub fn has_removal_choice(self) -> bool {
+        match self {
+            CacheKind::Registry => true,
+            CacheKind::Git | CacheKind::Binaries | CacheKind::Toolchains => false,
+        }
+    }
+
+    /// Whether entries are in use when projects reference them, installed binaries are only
+    /// judged by their last use
+    pub fn uses_references(self) -> bool {
+        self != CacheKind::Binaries
+    }
+
+    /// Directory the cache is located in
+    pub fn home(self) -> Option<PathBuf> {
+        match self {
+            CacheKind::Toolchains => rustup_home(),
+            CacheKind::Registry | CacheKind::Git | CacheKind::Binaries => cargo_home(),
+        }
+    }
+
+    /// What removing entries with `removal` deletes, e.g. for the journal
+    pub fn describe(self, removal: CacheRemoval) -> String {
+        if self.has_removal_choice() {
+            format!("{} {}", self.as_str(), removal.as_str())
+        } else {
+            self.as_str().to_string()
+        }
+    }
+
+    /// Analyze the cache in `home`, as returned by [`CacheKind::home`]
+    pub fn scan(self, home: &Path) -> Vec<CacheEntry> {
+        match self {
+            CacheKind::Registry => registry::scan_registry(home),
+            CacheKind::Git => git_cache::scan_git(home),
+            CacheKind::Binaries => installed::scan_installed(home),
+            CacheKind::Toolchains => {
+                let cwd = std::env::current_dir().ok();
+                let active = toolchain::active_toolchains(cwd.as_deref(), home);
+                let mut entries = toolchain::scan_toolchains(home);
+                for entry in &mut entries {
+                    entry.protected = toolchain::is_pinned(entry, &active);
+                }
+                entries
+            }
+        }
+    }
+
+    /// Record in `entries` whether any of the scanned projects still needs them
+    pub fn mark_in_use(self, entries: &mut [CacheEntry], references: &ProjectReferences) {
+        match self {
+            CacheKind::Registry => registry::mark_in_use(entries, &references.packages),
+            CacheKind::Git => git_cache::mark_in_use(entries, &references.packages),
+            CacheKind::Binaries => {}
+            CacheKind::Toolchains => toolchain::mark_in_use(entries, &references.toolchains),
+        }
+    }
+
+    /// Remove the parts of `entry` included in `removal`, uninstalling installed packages.
+    /// Returns the bytes freed.
+    pub fn remove(
+        self,
+        home: &Path,
+        entry: &CacheEntry,
+        removal: CacheRemoval,
+    ) -> anyhow::Result<u64> {
+        anyhow::ensure!(
+            !entry.protected,
+            "refusing to remove {}, it is in use",
+            entry.path(removal).display()
+        );
+        match self {
+            CacheKind::Binaries => installed::uninstall(home, entry),
+            CacheKind::Registry | CacheKind::Git | CacheKind::Toolchains => {
+                remove_entry(entry, removal)
+            }
+        }
+    }
+}
+
+/// What a file or directory of a cache entry is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PartKind {
+    /// Registry index, re-downloaded on demand
+    Index,
+    /// Downloaded `.crate` archive
+    Archive,
+    /// Sources extracted from the archive, re-extracted on demand
+    Source,
+    /// Bare clone of a git dependency, fetched again on demand
+    GitDatabase,
+    /// Working tree of one revision of a git dependency, checked out again on demand
+    GitCheckout,
+    /// Executable in `bin` installed by `cargo install`
+    Binary,
+    /// Installed rustup toolchain, installed again by rustup when a project needs it
+    Toolchain,
+}
+
+/// A file or directory belonging to a cache entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachePart {
+    pub kind: PartKind,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// One removable item of cargo's home, e.g. a crate version in a registry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub id: Uuid,
+    /// Registry or git repository directory the entry belongs to, the binaries of an
+    /// installed package or the host of a toolchain
+    pub group: String,
+    pub name: String,
+    pub version: String,
+    pub parts: Vec<CachePart>,
+    /// Latest modification or access of any part
+    pub last_used: SystemTime,
+    /// Whether a scanned project references the entry, `None` until the projects' lockfiles
+    /// and toolchain pins were read or for entries that are always kept like registry indices
+    pub in_use: Option<bool>,
+    /// Never removed, like the toolchain rustup runs by default or in the current directory
+    pub protected: bool,
+}
+
+impl CacheEntry {
+    /// Bytes freed by removing the parts included in `removal`
+    pub fn size(&self, removal: CacheRemoval) -> u64 {
+        self.parts
+            .iter()
+            .filter(|it| removal.includes(it.kind))
+            .map(|it| it.size)
+            .sum()
+    }
+
+    /// Path of the first part removed by `removal`, identifying the entry e.g. in the journal
+    pub fn path(&self, removal: CacheRemoval) -> &Path {
+        self.parts
+            .iter()
+            .find(|it| removal.includes(it.kind))
+            .or(self.parts.first())
+            .map_or(Path::new(""), |it| &it.path)
+    }
+
+    /// Whether no scanned project references the entry, it was not used for `older_than` and it
+    /// is not protected
+    pub fn is_unused(&self, older_than: Duration, now: SystemTime) -> bool {
+        !self.protected
+            && self.in_use == Some(false)
+            && now
+                .duration_since(self.last_used)
+                .is_ok_and(|it| it >= older_than)
+    }
+}
+
+/// Which parts of registry entries are removed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CacheRemoval {
+    /// Extracted sources, cargo extracts them again from the archives
+    #[default]
+    Sources,
+    /// Downloaded archives, keeping extracted sources
+    Archives,
+    All,
+}
+
+impl CacheRemoval {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheRemoval::Sources => "sources",
+            CacheRemoval::Archives => "archives",
+            CacheRemoval::All => "sources and archives",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CacheRemoval::Sources => CacheRemoval::Archives,
+            CacheRemoval::Archives => CacheRemoval::All,
+            CacheRemoval::All => CacheRemoval::Sources,
+        }
+    }
+
+    /// Whether parts of `kind` are removed. Registry indices need the network to be fetched
+    /// again and are only removed with `All`, the parts of other caches are always removed with
+    /// their entry.
+    pub fn includes(self, kind: PartKind) -> bool {
+        match self {
+            CacheRemoval::Sources => !matches!(kind, PartKind::Archive | PartKind::Index),
+            CacheRemoval::Archives => !matches!(kind, PartKind::Source | PartKind::Index),
+            CacheRemoval::All => true,
+        }
+    }
+}
+
+/// Remove the parts of `entry` included in `removal`, returning the bytes freed
+pub fn remove_entry(entry: &CacheEntry, removal: CacheRemoval) -> anyhow::Result<u64> {
+    let mut freed = 0;
+    for part in entry.parts.iter().filter(|it| removal.includes(it.kind)) {
+        let result = if part.path.is_dir() {
+            std::fs::remove_dir_all(&part.path)
+        } else {
+            std::fs::remove_file(&part.path)
+        };
+        match result {
+            Ok(()) => freed += part.size,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to remove {}", part.path.display()))
+            }
+        }
+    }
+    Ok(freed)
+}
+
+/// Size and latest modification or access of a file or directory tree. Only files count as
+/// accessed, listing a directory (like this function does) updates its access time.
+pub(crate) fn measure(path: &Path) -> (u64, SystemTime) {
+    let Ok(metadata) = path.metadata() else {
+        return (0, SystemTime::UNIX_EPOCH);
+    };
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if !metadata.is_dir() {
+        let accessed = metadata.accessed().unwrap_or(SystemTime::UNIX_EPOCH);
+        return (metadata.len(), modified.max(accessed));
+    }
+    path.read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.ok())
+        .map(|it| measure(&it.path()))
+        .fold((0, modified), |a, b| (a.0 + b.0, a.1.max(b.1)))
+}
+
+/// Subdirectories (or files) of `path` with their names, empty if `path` can not be read
+pub(crate) fn read_dir_entries(path: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(read_dir) = path.read_dir() else {
+        return vec![];
+    };
+    let mut entries = read_dir
+        .filter_map(|it| it.ok())
+        .map(|it| (it.file_name().to_string_lossy().into_owned(), it.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
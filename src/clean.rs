@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::fingerprint;
+use crate::{ProjectTargetAnalysis, UnitSizes};
+
+/// Which part of a target directory gets removed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanScope {
+    /// The whole target directory (`cargo clean`)
+    #[default]
+    All,
+    /// Only the dev profile output (`cargo clean --profile dev`)
+    Debug,
+    /// Only the release profile output (`cargo clean --release`)
+    Release,
+    /// Only generated documentation (`cargo clean --doc`)
+    Doc,
+    /// Only build units compiled by another compiler than the latest one, always removed
+    /// directly as cargo has no equivalent
+    StaleToolchains,
+    /// Only build units and incremental caches not used for `sweep-older-than`, always removed
+    /// directly as cargo has no equivalent
+    Sweep,
+    /// Only build units superseded by another variant of the same crate, always removed
+    /// directly as cargo has no equivalent
+    DedupeDeps,
+    /// Only rust-analyzer's target directory and the metadata of `cargo check` and clippy,
+    /// always removed directly as cargo has no equivalent
+    Check,
+}
+
+impl CleanScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CleanScope::All => "all",
+            CleanScope::Debug => "debug",
+            CleanScope::Release => "release",
+            CleanScope::Doc => "doc",
+            CleanScope::StaleToolchains => "stale-toolchains",
+            CleanScope::Sweep => "sweep",
+            CleanScope::DedupeDeps => "dedupe-deps",
+            CleanScope::Check => "check",
+        }
+    }
+}
+
+/// Everything deciding what cleaning a project removes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CleanOptions {
+    pub scope: CleanScope,
+    pub backend: DeletionBackend,
+    /// With [`CleanScope::Sweep`], what was not used for this long is removed
+    pub sweep_older_than: Duration,
+}
+
+/// How files are removed from the disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeletionBackend {
+    /// Run `cargo clean` inside the project
+    #[default]
+    Cargo,
+    /// Remove the directories directly without invoking cargo
+    Native,
+}
+
+impl DeletionBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeletionBackend::Cargo => "cargo",
+            DeletionBackend::Native => "native",
+        }
+    }
+}
+
+/// Remove the `scope` part of the target directory of `project`
+pub fn clean_project(
+    project: &ProjectTargetAnalysis,
+    options: &CleanOptions,
+) -> anyhow::Result<()> {
+    match options.scope {
+        CleanScope::All => remove_dirs(project, options.backend, &[], project.artifact_dirs()),
+        CleanScope::Debug => remove_dirs(
+            project,
+            options.backend,
+            &["--profile", "dev"],
+            profile_dirs(project, "debug"),
+        ),
+        CleanScope::Release => remove_dirs(
+            project,
+            options.backend,
+            &["--release"],
+            profile_dirs(project, "release"),
+        ),
+        CleanScope::Doc => remove_dirs(project, options.backend, &["--doc"], doc_dirs(project)),
+        CleanScope::StaleToolchains => {
+            let units = scan_units(project);
+            fingerprint::remove_units(fingerprint::stale_toolchain_units(&units))
+        }
+        CleanScope::Sweep => {
+            fingerprint::sweep(&project.artifact_dirs(), sweep_cutoff(options)).map(|_| ())
+        }
+        CleanScope::DedupeDeps => {
+            let units = scan_units(project);
+            fingerprint::remove_units(fingerprint::superseded_units(&units))
+        }
+        CleanScope::Check => {
+            let units = scan_units(project);
+            fingerprint::check_artifacts(&project.target_path, &units).remove()
+        }
+    }
+}
+
+/// Bytes cleaning `project` with `options` would free, measured without removing anything
+pub fn reclaimable_size(project: &ProjectTargetAnalysis, options: &CleanOptions) -> u64 {
+    let measure = |dirs: Vec<PathBuf>| {
+        dirs.iter()
+            .map(|it| ProjectTargetAnalysis::recursive_scan_target(it).0)
+            .sum()
+    };
+    let unit_sizes = || {
+        project
+            .unit_sizes
+            .unwrap_or_else(|| UnitSizes::measure(&project.artifact_dirs()))
+    };
+    match options.scope {
+        CleanScope::All => project.size,
+        CleanScope::Debug => measure(profile_dirs(project, "debug")),
+        CleanScope::Release => measure(profile_dirs(project, "release")),
+        CleanScope::Doc => measure(doc_dirs(project)),
+        CleanScope::StaleToolchains => unit_sizes().stale_toolchain,
+        CleanScope::Sweep => {
+            fingerprint::plan_sweep(&project.artifact_dirs(), sweep_cutoff(options)).size()
+        }
+        CleanScope::DedupeDeps => unit_sizes().duplicate,
+        CleanScope::Check => unit_sizes().check,
+    }
+}
+
+/// The `profile` directories in the target and build directories of `project`
+fn profile_dirs(project: &ProjectTargetAnalysis, profile: &str) -> Vec<PathBuf> {
+    project
+        .artifact_dirs()
+        .iter()
+        .map(|it| it.join(profile))
+        .collect()
+}
+
+/// Documentation is only written to the target directory
+fn doc_dirs(project: &ProjectTargetAnalysis) -> Vec<PathBuf> {
+    vec![project.target_path.join("doc")]
+}
+
+/// Units last used before this time are swept, all of them when `sweep_older_than` reaches back
+/// before 1970
+fn sweep_cutoff(options: &CleanOptions) -> SystemTime {
+    SystemTime::now()
+        .checked_sub(options.sweep_older_than)
+        .unwrap_or(UNIX_EPOCH)
+}
+
+/// Remove `dirs` with `cargo clean <cargo_args>`, or directly with the native backend
+fn remove_dirs(
+    project: &ProjectTargetAnalysis,
+    backend: DeletionBackend,
+    cargo_args: &[&str],
+    dirs: Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    // Without a manifest there is nothing cargo could clean
+    let backend = match project.orphaned {
+        true => DeletionBackend::Native,
+        false => backend,
+    };
+    let dirs = match backend {
+        DeletionBackend::Cargo => {
+            cargo_clean(&project.project_path, cargo_args)?;
+            // Toolchains without `build.build-dir` support leave it behind
+            dirs.into_iter()
+                .filter(|it| {
+                    project
+                        .build_dir
+                        .as_ref()
+                        .is_some_and(|dir| it.starts_with(dir))
+                })
+                .collect()
+        }
+        DeletionBackend::Native => dirs,
+    };
+    for dir in dirs {
+        remove_dir(&dir)?;
+    }
+    Ok(())
+}
+
+/// The build units of the target and build directories of `project`
+fn scan_units(project: &ProjectTargetAnalysis) -> Vec<fingerprint::BuildUnit> {
+    project
+        .artifact_dirs()
+        .iter()
+        .flat_map(|it| fingerprint::scan_units(it))
+        .collect()
+}
+
+/// Clean `project` like [`clean_project`] and return how many bytes were freed
+pub fn clean_and_measure(
+    project: &ProjectTargetAnalysis,
+    options: &CleanOptions,
+) -> anyhow::Result<u64> {
+    clean_project(project, options)?;
+    let remaining = project
+        .artifact_dirs()
+        .iter()
+        .map(|it| ProjectTargetAnalysis::recursive_scan_target(it).0)
+        .sum::<u64>();
+    Ok(project.size.saturating_sub(remaining))
+}
+
+fn cargo_clean(project_path: &Path, args: &[&str]) -> anyhow::Result<()> {
+    let mut command = Command::new("cargo");
+    command.arg("clean").args(args);
+    let status = command
+        .current_dir(project_path)
+        .stderr(Stdio::null())
+        .status()
+        .context("failed to execute cargo")?;
+    if !status.success() {
+        bail!("`cargo clean` failed in {}", project_path.display());
+    }
+    Ok(())
+}
+
+fn remove_dir(path: &Path) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    std::fs::remove_dir_all(path).with_context(|| format!("failed to remove {}", path.display()))
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sort::SortKey;
+
+/// Column of the project table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Column {
+    Path,
+    Name,
+    Size,
+    /// Time since the target was last modified, e.g. `3 months ago`
+    Age,
+    /// Space the target occupies on disk
+    AllocatedSize,
+    TargetPath,
+    GitBranch,
+    /// Toolchain pinned by `rust-toolchain`
+    Toolchain,
+    /// Number of packages of a workspace root
+    WorkspaceMembers,
+    /// Release of the compiler the target was last built with
+    BuiltWith,
+}
+
+impl Column {
+    /// Every column in the order the column chooser lists them
+    pub const ALL: [Column; 10] = [
+        Column::Path,
+        Column::Name,
+        Column::Size,
+        Column::Age,
+        Column::AllocatedSize,
+        Column::TargetPath,
+        Column::GitBranch,
+        Column::Toolchain,
+        Column::WorkspaceMembers,
+        Column::BuiltWith,
+    ];
+
+    /// Columns shown unless configured otherwise
+    pub const DEFAULT: [Column; 5] = [
+        Column::Path,
+        Column::Name,
+        Column::Size,
+        Column::Age,
+        Column::BuiltWith,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Name => "name",
+            Column::Size => "size",
+            Column::Age => "age",
+            Column::AllocatedSize => "allocated-size",
+            Column::TargetPath => "target-path",
+            Column::GitBranch => "git-branch",
+            Column::Toolchain => "toolchain",
+            Column::WorkspaceMembers => "workspace-members",
+            Column::BuiltWith => "built-with",
+        }
+    }
+
+    /// Title in the table header, sizes get their unit appended
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Path => "Project Path",
+            Column::Name => "Project Name",
+            Column::Size => "Size",
+            Column::Age => "Age",
+            Column::AllocatedSize => "Allocated",
+            Column::TargetPath => "Target Path",
+            Column::GitBranch => "Git Branch",
+            Column::Toolchain => "Toolchain",
+            Column::WorkspaceMembers => "Members",
+            Column::BuiltWith => "Built With",
+        }
+    }
+
+    /// Key ordering the projects by this column
+    pub fn sort_key(self) -> SortKey {
+        match self {
+            Column::Path => SortKey::Path,
+            Column::Name => SortKey::Name,
+            Column::Size => SortKey::Size,
+            Column::Age => SortKey::LastModified,
+            Column::AllocatedSize => SortKey::AllocatedSize,
+            Column::TargetPath => SortKey::TargetPath,
+            Column::GitBranch => SortKey::GitBranch,
+            Column::Toolchain => SortKey::Toolchain,
+            Column::WorkspaceMembers => SortKey::WorkspaceMembers,
+            Column::BuiltWith => SortKey::BuiltWith,
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::clean::{CleanOptions, CleanScope, DeletionBackend};
+use crate::columns::Column;
+use crate::sort::SortOrder;
+use crate::units::{ByteSize, HumanDuration, SizeUnit};
+use crate::GIB_SIZE;
+
+/// File name of the project-local configuration, looked up from the working directory upwards
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".cargo-cleaner.toml";
+
+/// One layer of configuration, read from a file or built from the command line.
+///
+/// Layers are merged in the order user config (or `--config`), project-local config, CLI flags.
+/// A later layer overrides the settings it defines, except `excludes` and `protected` which
+/// accumulate.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub search_roots: Option<Vec<PathBuf>>,
+    pub excludes: Option<Vec<String>>,
+    pub scan_workers: Option<usize>,
+    pub size_unit: Option<SizeUnit>,
+    pub sort: Option<SortOrder>,
+    /// Columns of the project table in the TUI
+    pub columns: Option<Vec<Column>>,
+    pub clean_scope: Option<CleanScope>,
+    pub deletion_backend: Option<DeletionBackend>,
+    pub dry_run: Option<bool>,
+    /// Project paths or globs that are never cleaned automatically
+    pub protected: Option<Vec<String>>,
+    /// Unreferenced cache entries used more recently than this are kept
+    pub cache_older_than: Option<HumanDuration>,
+    /// The `sweep` clean scope removes what was not used for this long
+    pub sweep_older_than: Option<HumanDuration>,
+    pub daemon: Option<DaemonLayer>,
+}
+
+/// The `[daemon]` table of a config layer
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DaemonLayer {
+    pub min_free: Option<ByteSize>,
+    pub target_free: Option<ByteSize>,
+    pub interval: Option<HumanDuration>,
+    pub poll_interval: Option<HumanDuration>,
+    pub older_than: Option<HumanDuration>,
+}
+
+impl DaemonLayer {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            min_free: other.min_free.or(self.min_free),
+            target_free: other.target_free.or(self.target_free),
+            interval: other.interval.or(self.interval),
+            poll_interval: other.poll_interval.or(self.poll_interval),
+            older_than: other.older_than.or(self.older_than),
+        }
+    }
+}
+
+impl ConfigLayer {
+    /// Read a layer from a TOML file. Relative paths are resolved against the file's directory.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        let layer: Self = toml::from_str(&text)
+            .with_context(|| format!("failed to parse config file {}", path.display()))?;
+        Ok(layer.resolve_paths(path.parent().unwrap_or(Path::new("."))))
+    }
+
+    /// Expand `~` and make relative search roots and exclude paths absolute
+    pub fn resolve_paths(mut self, base: &Path) -> Self {
+        if let Some(roots) = &mut self.search_roots {
+            for root in roots.iter_mut() {
+                *root = resolve_path(base, root);
+            }
+        }
+        if let Some(excludes) = &mut self.excludes {
+            for exclude in excludes.iter_mut() {
+                // Plain names match a directory anywhere, only path-like entries are resolved
+                if exclude.contains('/') {
+                    *exclude = resolve_path(base, Path::new(exclude))
+                        .to_string_lossy()
+                        .into_owned();
+                }
+            }
+        }
+        if let Some(protected) = &mut self.protected {
+            for pattern in protected.iter_mut() {
+                *pattern = resolve_path(base, Path::new(pattern))
+                    .to_string_lossy()
+                    .into_owned();
+            }
+        }
+        self
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let daemon = match (self.daemon, other.daemon) {
+            (Some(a), Some(b)) => Some(a.merge(b)),
+            (a, b) => b.or(a),
+        };
+        Self {
+            search_roots: other.search_roots.or(self.search_roots),
+            excludes: concat(self.excludes, other.excludes),
+            scan_workers: other.scan_workers.or(self.scan_workers),
+            size_unit: other.size_unit.or(self.size_unit),
+            sort: other.sort.or(self.sort),
+            columns: other.columns.or(self.columns),
+            clean_scope: other.clean_scope.or(self.clean_scope),
+            deletion_backend: other.deletion_backend.or(self.deletion_backend),
+            dry_run: other.dry_run.or(self.dry_run),
+            protected: concat(self.protected, other.protected),
+            cache_older_than: other.cache_older_than.or(self.cache_older_than),
+            sweep_older_than: other.sweep_older_than.or(self.sweep_older_than),
+            daemon,
+        }
+    }
+}
+
+fn concat(a: Option<Vec<String>>, b: Option<Vec<String>>) -> Option<Vec<String>> {
+    match (a, b) {
+        (Some(mut a), Some(b)) => {
+            a.extend(b);
+            Some(a)
+        }
+        (a, b) => b.or(a),
+    }
+}
+
+/// The effective configuration after merging all layers with the defaults
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    pub search_roots: Vec<PathBuf>,
+    pub excludes: Vec<String>,
+    pub scan_workers: usize,
+    pub size_unit: SizeUnit,
+    pub sort: SortOrder,
+    /// Columns of the project table in the TUI
+    pub columns: Vec<Column>,
+    pub clean_scope: CleanScope,
+    pub deletion_backend: DeletionBackend,
+    pub dry_run: bool,
+    pub protected: Vec<String>,
+    /// Entries of cargo's home that no scanned project references are only offered for removal
+    /// when they were not used for this long
+    pub cache_older_than: HumanDuration,
+    /// The `sweep` clean scope removes build units and incremental caches neither modified nor
+    /// accessed for this long
+    pub sweep_older_than: HumanDuration,
+    pub daemon: DaemonConfig,
+    /// Config files that contributed to this configuration, lowest precedence first
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+/// Cleanup policy of `cargo cleaner daemon`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DaemonConfig {
+    /// Start cleaning when a filesystem holding projects has less space available than this
+    pub min_free: ByteSize,
+    /// Keep cleaning until this much is available again
+    pub target_free: ByteSize,
+    /// Time between full rescans
+    pub interval: HumanDuration,
+    /// Time between free-space checks, a low filesystem triggers an immediate rescan
+    pub poll_interval: HumanDuration,
+    /// Targets used more recently than this are never cleaned
+    pub older_than: HumanDuration,
+}
+
+impl From<DaemonLayer> for DaemonConfig {
+    fn from(layer: DaemonLayer) -> Self {
+        let min_free = layer.min_free.unwrap_or(ByteSize(10 * GIB_SIZE));
+        Self {
+            min_free,
+            target_free: layer
+                .target_free
+                .unwrap_or(ByteSize(min_free.0 * 2))
+                .max(min_free),
+            interval: layer
+                .interval
+                .unwrap_or(HumanDuration(Duration::from_secs(60 * 60))),
+            poll_interval: layer
+                .poll_interval
+                .unwrap_or(HumanDuration(Duration::from_secs(60))),
+            older_than: layer
+                .older_than
+                .unwrap_or(HumanDuration(Duration::from_secs(7 * 24 * 60 * 60))),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_layer(ConfigLayer::default(), vec![])
+    }
+}
+
+impl Config {
+    /// Location of the user configuration, `$XDG_CONFIG_HOME/cargo-cleaner/config.toml`
+    /// falling back to `~/.config/cargo-cleaner/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|it| it.is_absolute())
+            .or_else(|| home_dir().map(|it| it.join(".config")))
+            .map(|it| it.join("cargo-cleaner").join("config.toml"))
+    }
+
+    /// Find the nearest project-local config file in `cwd` or its ancestors
+    pub fn find_project_config(cwd: &Path) -> Option<PathBuf> {
+        cwd.ancestors()
+            .map(|it| it.join(PROJECT_CONFIG_FILE_NAME))
+            .find(|it| it.is_file())
+    }
+
+    /// Load and merge the configuration layers.
+    ///
+    /// `explicit_path` replaces the user config and must exist, the user config is optional.
+    pub fn load(
+        explicit_path: Option<&Path>,
+        cwd: &Path,
+        cli: ConfigLayer,
+    ) -> anyhow::Result<Self> {
+        let mut sources = vec![];
+        let mut layer = ConfigLayer::default();
+
+        let user_config = match explicit_path {
+            Some(path) => Some(path.to_owned()),
+            None => Self::default_path().filter(|it| it.is_file()),
+        };
+        let project_config = Self::find_project_config(cwd);
+        for path in user_config.into_iter().chain(project_config) {
+            layer = layer.merge(ConfigLayer::from_path(&path)?);
+            sources.push(path);
+        }
+
+        let config = Self::from_layer(layer.merge(cli.resolve_paths(cwd)), sources);
+        anyhow::ensure!(
+            !config.daemon.poll_interval.0.is_zero(),
+            "daemon.poll-interval must be longer than zero"
+        );
+        Ok(config)
+    }
+
+    fn from_layer(layer: ConfigLayer, sources: Vec<PathBuf>) -> Self {
+        Self {
+            search_roots: layer
+                .search_roots
+                .filter(|it| !it.is_empty())
+                .unwrap_or_else(|| home_dir().into_iter().collect()),
+            excludes: layer.excludes.unwrap_or_default(),
+            scan_workers: layer
+                .scan_workers
+                .unwrap_or_else(|| (num_cpus::get() - 1).max(1)),
+            size_unit: layer.size_unit.unwrap_or_default(),
+            sort: layer.sort.unwrap_or_default(),
+            columns: layer
+                .columns
+                .filter(|it| !it.is_empty())
+                .unwrap_or_else(|| Column::DEFAULT.to_vec()),
+            clean_scope: layer.clean_scope.unwrap_or_default(),
+            deletion_backend: layer.deletion_backend.unwrap_or_default(),
+            dry_run: layer.dry_run.unwrap_or_default(),
+            protected: layer.protected.unwrap_or_default(),
+            cache_older_than: layer
+                .cache_older_than
+                .unwrap_or(HumanDuration(Duration::from_secs(30 * 24 * 60 * 60))),
+            sweep_older_than: layer
+                .sweep_older_than
+                .unwrap_or(HumanDuration(Duration::from_secs(30 * 24 * 60 * 60))),
+            daemon: layer.daemon.unwrap_or_default().into(),
+            sources,
+        }
+    }
+
+    /// How the clean commands clean projects
+    pub fn clean_options(&self) -> CleanOptions {
+        CleanOptions {
+            scope: self.clean_scope,
+            backend: self.deletion_backend,
+            sweep_older_than: self.sweep_older_than.0,
+        }
+    }
+
+    /// Whether `project_path` matches one of the `protected` paths or globs
+    pub fn is_protected(&self, project_path: &Path) -> bool {
+        self.protected.iter().any(|it| {
+            project_path.starts_with(it)
+                || glob::Pattern::new(it).is_ok_and(|pattern| pattern.matches_path(project_path))
+        })
+    }
+
+    /// Render the configuration as TOML, prefixed with the list of files it was read from
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        let mut out = String::new();
+        if self.sources.is_empty() {
+            out.push_str("# no config files found, using defaults\n");
+        }
+        for source in &self.sources {
+            out.push_str(&format!("# loaded from {}\n", source.display()));
+        }
+        out.push_str(&toml::to_string(self)?);
+        Ok(out)
+    }
+}
+
+fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    let path = match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_owned(),
+    };
+    if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::SortKey;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let file: ConfigLayer = toml::from_str(
+            r#"
+            search-roots = ["/work"]
+            excludes = ["node_modules"]
+            scan-workers = 2
+            sort = "name"
+            columns = ["name", "git-branch", "size"]
+            deletion-backend = "native"
+            "#,
+        )
+        .unwrap();
+        let cli = ConfigLayer {
+            scan_workers: Some(8),
+            excludes: Some(vec!["vendor".to_string()]),
+            ..Default::default()
+        };
+
+        let config = Config::from_layer(file.merge(cli), vec![]);
+        assert_eq!(config.search_roots, vec![PathBuf::from("/work")]);
+        assert_eq!(config.scan_workers, 8);
+        assert_eq!(config.excludes, vec!["node_modules", "vendor"]);
+        assert_eq!(config.sort.key, SortKey::Name);
+        assert!(!config.sort.descending);
+        assert_eq!(
+            config.columns,
+            [Column::Name, Column::GitBranch, Column::Size]
+        );
+        assert_eq!(config.deletion_backend, DeletionBackend::Native);
+        assert_eq!(config.clean_scope, CleanScope::All);
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_config_dir() {
+        let layer = ConfigLayer {
+            search_roots: Some(vec![PathBuf::from("projects")]),
+            excludes: Some(vec!["target".to_string(), "keep/this".to_string()]),
+            ..Default::default()
+        }
+        .resolve_paths(Path::new("/home/me"));
+
+        assert_eq!(
+            layer.search_roots,
+            Some(vec![PathBuf::from("/home/me/projects")])
+        );
+        assert_eq!(
+            layer.excludes,
+            Some(vec!["target".to_string(), "/home/me/keep/this".to_string()])
+        );
+    }
+
+    #[test]
+    fn zero_poll_interval_is_rejected() {
+        let cli = ConfigLayer {
+            daemon: Some(DaemonLayer {
+                poll_interval: Some(HumanDuration(Duration::ZERO)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let cwd = TempDir::new("config");
+        let config_path = cwd.join("config.toml");
+        std::fs::write(&config_path, "").unwrap();
+
+        assert!(Config::load(Some(&config_path), &cwd, cli).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<ConfigLayer>("serch-roots = []").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::clean::{clean_and_measure, reclaimable_size};
+use crate::config::Config;
+use crate::disk::{file_system_usage, DiskSpace};
+use crate::filter::ProjectFilter;
+use crate::goal::plan_min_free;
+use crate::journal::{self, JournalEntry};
+use crate::list::collect_projects;
+use crate::units::format_timestamp;
+use crate::ProjectTargetAnalysis;
+
+/// Granularity in which sleeping checks whether the daemon should stop
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+fn log(message: impl Display) {
+    eprintln!("[{}] {message}", format_timestamp(SystemTime::now()));
+}
+
+/// Watch the free space of the filesystems holding projects and clean the stalest unprotected
+/// targets when one of them runs low, until `stop` is set.
+///
+/// Projects are rescanned every `daemon.interval`, and immediately when polling notices a
+/// filesystem dropping below `daemon.min-free`. Low filesystems are cleaned until they have
+/// `daemon.target-free` available. With `once` a single scan and cleanup is done.
+pub fn run(config: &Config, once: bool, stop: &AtomicBool) -> anyhow::Result<()> {
+    let policy = &config.daemon;
+    log(format!(
+        "watching {} (min free {}, target free {})",
+        config
+            .search_roots
+            .iter()
+            .map(|it| it.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        policy.min_free,
+        policy.target_free
+    ));
+
+    let mut watched = config.search_roots.clone();
+    let mut low_at_last_scan = HashSet::new();
+    let mut next_scan = Instant::now();
+    loop {
+        let low = low_devices(&watched, policy.min_free.0);
+        let newly_low = low.difference(&low_at_last_scan).next().is_some();
+        if next_scan <= Instant::now() || newly_low {
+            if newly_low {
+                log("free space dropped below the threshold, rescanning");
+            }
+            let (projects, errors) = collect_projects(config, &ProjectFilter::default());
+            for err in errors {
+                log(format!("warning: {err:#}"));
+            }
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            watched = config
+                .search_roots
+                .iter()
+                .cloned()
+                .chain(projects.iter().map(|it| it.target_path.clone()))
+                .collect();
+            low_at_last_scan = low_devices(&watched, policy.min_free.0);
+            clean_low_file_systems(config, &projects, &low_at_last_scan, stop);
+            next_scan = Instant::now() + policy.interval.0;
+        }
+
+        if once {
+            return Ok(());
+        }
+        sleep(policy.poll_interval.0, stop);
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    log("stopping");
+    Ok(())
+}
+
+/// Devices of the filesystems containing `paths` that have less than `min_free` available
+fn low_devices(paths: &[PathBuf], min_free: u64) -> HashSet<u64> {
+    paths
+        .iter()
+        .filter_map(|it| file_system_usage(it))
+        .filter(|it| it.free < min_free)
+        .map(|it| it.device)
+        .collect()
+}
+
+/// Clean the stalest candidates on the `low` filesystems, the remaining ones are skipped once
+/// `stop` is set
+fn clean_low_file_systems(
+    config: &Config,
+    projects: &[ProjectTargetAnalysis],
+    low: &HashSet<u64>,
+    stop: &AtomicBool,
+) {
+    if low.is_empty() {
+        return;
+    }
+    let policy = &config.daemon;
+    let now = SystemTime::now();
+    let mut disk_space = DiskSpace::default();
+    let candidates = projects
+        .iter()
+        .filter(|it| !config.is_protected(&it.project_path))
+        .filter(|it| {
+            now.duration_since(it.last_modified)
+                .is_ok_and(|age| age >= policy.older_than.0)
+        })
+        .filter(|it| {
+            disk_space
+                .usage_of(it)
+                .is_some_and(|usage| low.contains(&usage.device))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // The configured scope may free only part of each target
+    let options = config.clean_options();
+    let plan = plan_min_free(
+        &candidates,
+        policy.target_free.0,
+        &mut disk_space,
+        now,
+        |it| reclaimable_size(it, &options),
+    );
+    if !plan.reached() {
+        log(format!(
+            "only {} of {} can be freed from unprotected targets older than {}",
+            config.size_unit.format(plan.reclaimed),
+            config.size_unit.format(plan.goal),
+            policy.older_than
+        ));
+    }
+
+    for project in candidates
+        .iter()
+        .filter(|it| plan.selected.contains(&it.id))
+    {
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let path = project.project_path.display();
+        if config.dry_run {
+            log(format!(
+                "would clean {path} ({})",
+                config.size_unit.format(plan.sizes[&project.id])
+            ));
+            continue;
+        }
+        let result = clean_and_measure(project, &options);
+        match &result {
+            Ok(freed) => log(format!(
+                "cleaned {path} ({})",
+                config.size_unit.format(*freed)
+            )),
+            Err(err) => log(format!("failed to clean {path}: {err:#}")),
+        }
+        let entry = JournalEntry::new(
+            "daemon",
+            &project.project_path,
+            config.clean_scope.as_str(),
+            &result,
+        );
+        if let Err(err) = journal::append(&entry) {
+            log(format!("warning: {err:#}"));
+        }
+    }
+}
+
+/// Sleep for `duration` or until `stop` is set
+fn sleep(duration: Duration, stop: &AtomicBool) {
+    let until = Instant::now() + duration;
+    while !stop.load(Ordering::Relaxed) {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        std::thread::sleep(left.min(STOP_CHECK_INTERVAL));
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::ProjectTargetAnalysis;
+
+/// How long measured free space is reused before asking the OS again
+const USAGE_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Space on the filesystem containing a path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSystemUsage {
+    /// Device id of the filesystem, identical for all paths on the same mount
+    pub device: u64,
+    /// Topmost directory that is still on the same filesystem
+    pub mount_point: PathBuf,
+    /// Bytes available to unprivileged users
+    pub free: u64,
+    pub total: u64,
+}
+
+/// Listed and selected target sizes on one filesystem
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSystemSummary {
+    pub usage: FileSystemUsage,
+    pub listed: u64,
+    pub selected: u64,
+}
+
+impl FileSystemSummary {
+    /// Free space after deleting the selected targets
+    pub fn projected_free(&self) -> u64 {
+        (self.usage.free + self.selected).min(self.usage.total)
+    }
+}
+
+/// Measure the filesystem containing `path`. Paths that do not exist (yet) are measured on
+/// their nearest existing ancestor.
+pub fn file_system_usage(path: &Path) -> Option<FileSystemUsage> {
+    let path = path.ancestors().find(|it| it.exists())?;
+    let device = device_id(path)?;
+    let (free, total) = statvfs(path)?;
+    let mount_point = path
+        .canonicalize()
+        .ok()?
+        .ancestors()
+        .take_while(|it| device_id(it) == Some(device))
+        .last()?
+        .to_owned();
+    Some(FileSystemUsage {
+        device,
+        mount_point,
+        free,
+        total,
+    })
+}
+
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.metadata().ok().map(|it| it.dev())
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL terminated and `stat` is only read after statvfs succeeded
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    let fragment_size = stat.f_frsize as u64;
+    Some((
+        stat.f_bavail as u64 * fragment_size,
+        stat.f_blocks as u64 * fragment_size,
+    ))
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Bytes the file of `metadata` takes up on disk, which differs from its length for sparse
+/// files, compressed filesystems and the unused rest of its last block
+#[cfg(unix)]
+pub fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+pub fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Device and inode of a file with several hard links, so its blocks are counted once
+#[cfg(unix)]
+pub fn hard_link_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn hard_link_id(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Caches which filesystem a target lives on and how much space it has, so the status bar can
+/// be drawn on every frame without hammering the OS
+#[derive(Default)]
+pub struct DiskSpace {
+    devices: HashMap<PathBuf, Option<u64>>,
+    usages: HashMap<u64, (Instant, FileSystemUsage)>,
+}
+
+impl DiskSpace {
+    /// Filesystem usage for the target of `project`, `None` on platforms without statvfs
+    pub fn usage_of(&mut self, project: &ProjectTargetAnalysis) -> Option<FileSystemUsage> {
+        let device = *self
+            .devices
+            .entry(project.target_path.clone())
+            .or_insert_with(|| file_system_usage(&project.target_path).map(|it| it.device));
+        let device = device?;
+
+        match self.usages.get(&device) {
+            Some((measured_at, usage)) if measured_at.elapsed() < USAGE_REFRESH_INTERVAL => {
+                Some(usage.clone())
+            }
+            _ => {
+                let usage = file_system_usage(&project.target_path)?;
+                self.usages.insert(device, (Instant::now(), usage.clone()));
+                Some(usage)
+            }
+        }
+    }
+
+    /// Forget measured free space, e.g. after deleting something
+    pub fn invalidate(&mut self) {
+        self.usages.clear();
+    }
+
+    /// Group listed and selected sizes by filesystem, ordered by mount point
+    pub fn summarize(
+        &mut self,
+        items: &[ProjectTargetAnalysis],
+        selected: &HashSet<Uuid>,
+    ) -> Vec<FileSystemSummary> {
+        let mut summaries = HashMap::<u64, FileSystemSummary>::new();
+        for item in items {
+            let Some(usage) = self.usage_of(item) else {
+                continue;
+            };
+            let summary = summaries
+                .entry(usage.device)
+                .or_insert_with(|| FileSystemSummary {
+                    usage,
+                    listed: 0,
+                    selected: 0,
+                });
+            summary.listed += item.size;
+            if selected.contains(&item.id) {
+                summary.selected += item.size;
+            }
+        }
+        let mut summaries = summaries.into_values().collect::<Vec<_>>();
+        summaries.sort_by(|a, b| a.usage.mount_point.cmp(&b.usage.mount_point));
+        summaries
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_paths_are_measured_on_existing_ancestor() {
+        let root = file_system_usage(Path::new("/")).unwrap();
+        assert_eq!(root.mount_point, PathBuf::from("/"));
+        assert!(root.free <= root.total);
+
+        let missing = file_system_usage(Path::new("/does/not/exist/target")).unwrap();
+        assert_eq!(missing.device, root.device);
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, SystemTime};
+
+use glob::Pattern;
+use regex::Regex;
+
+use crate::ProjectTargetAnalysis;
+
+/// Criteria a project has to meet to be listed or cleaned. Unset criteria match everything.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectFilter {
+    /// Smallest target size in bytes. When unset, projects without build output are skipped.
+    pub min_size: Option<u64>,
+    /// Only projects whose target was last modified longer ago than this
+    pub older_than: Option<Duration>,
+    /// Glob matched against the project path
+    pub path_glob: Option<Pattern>,
+    /// Regex searched in the project name
+    pub name_regex: Option<Regex>,
+    /// Only projects last built by a compiler that is no longer installed
+    pub compiler_missing: bool,
+}
+
+impl ProjectFilter {
+    pub fn matches(&self, project: &ProjectTargetAnalysis, now: SystemTime) -> bool {
+        let size_ok = match self.min_size {
+            Some(min_size) => project.size >= min_size,
+            None => project.size > 0,
+        };
+        let age_ok = self.older_than.is_none_or(|older_than| {
+            now.duration_since(project.last_modified)
+                .is_ok_and(|age| age >= older_than)
+        });
+        let path_ok = self
+            .path_glob
+            .as_ref()
+            .is_none_or(|glob| glob.matches_path(&project.project_path));
+        let name_ok = self.name_regex.as_ref().is_none_or(|regex| {
+            project
+                .project_name
+                .as_deref()
+                .is_some_and(|name| regex.is_match(name))
+        });
+        let compiler_ok = !self.compiler_missing || project.compiler_installed == Some(false);
+        size_ok && age_ok && path_ok && name_ok && compiler_ok
+    }
+}
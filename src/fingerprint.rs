@@ -0,0 +1,844 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::cargo_home::{measure, read_dir_entries};
+use crate::toolchain::rustup_home;
+use crate::ProjectTargetAnalysis;
+
+/// One compilation unit of a profile directory (e.g. `target/debug`), identified by the
+/// `<package>-<hash>` directory cargo keeps its fingerprint in. The hash also names the unit's
+/// files in `deps` and `build`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildUnit {
+    /// Profile directory the unit belongs to
+    pub profile_dir: PathBuf,
+    pub package: String,
+    pub hash: String,
+    /// Hash of the compiler version cargo recorded in the fingerprint
+    pub rustc: Option<u64>,
+    /// What the unit compiles, shared by the variants of a crate that only differ in features,
+    /// dependencies or compiler
+    pub target: Option<UnitTarget>,
+    /// Fingerprint hash of the unit, by which its dependents refer to it
+    pub fingerprint: Option<u64>,
+    /// Fingerprint hashes of the dependencies the unit was built against
+    pub deps: Vec<u64>,
+    /// Fingerprint directory, build script directory and the files in `deps` and `examples` of
+    /// the unit
+    pub paths: Vec<PathBuf>,
+    pub size: u64,
+    /// Latest modification of any file of the unit, or access of a file only cargo and rustc
+    /// read (we read the fingerprint and dep-info files ourselves)
+    pub last_used: SystemTime,
+}
+
+impl BuildUnit {
+    /// Whether the unit only produced metadata, as `cargo check` and clippy do
+    pub fn is_check_only(&self) -> bool {
+        let mut outputs = self.paths[1..]
+            .iter()
+            .filter(|it| it.extension().is_none_or(|it| it != "d"))
+            .peekable();
+        outputs.peek().is_some() && outputs.all(|it| it.extension().is_some_and(|it| it == "rmeta"))
+    }
+}
+
+/// Identity of a build unit apart from its metadata hash, from the fingerprint cargo records
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnitTarget {
+    /// Name of the fingerprint, e.g. `lib-serde` or `run-build-script-build-script-build`
+    pub name: String,
+    pub target: u64,
+    pub path: u64,
+    pub profile: u64,
+    pub compile_kind: u64,
+}
+
+#[derive(Deserialize)]
+struct Fingerprint {
+    rustc: u64,
+    #[serde(default)]
+    target: u64,
+    #[serde(default)]
+    path: u64,
+    #[serde(default)]
+    profile: u64,
+    #[serde(default)]
+    compile_kind: u64,
+    /// `[package id hash, name, public, fingerprint hash]` per dependency
+    #[serde(default)]
+    deps: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct RustcInfo {
+    #[serde(default)]
+    outputs: HashMap<String, RustcOutput>,
+}
+
+#[derive(Deserialize)]
+struct RustcOutput {
+    #[serde(default)]
+    stdout: String,
+}
+
+/// Split a `<name>-<16 hex digit hash>` file stem
+fn split_hash(stem: &str) -> Option<(&str, &str)> {
+    stem.rsplit_once('-')
+        .filter(|(_, hash)| hash.len() == 16 && hash.chars().all(|it| it.is_ascii_hexdigit()))
+}
+
+/// Directories below `target_path` holding the output of one profile, like `debug` or
+/// `x86_64-unknown-linux-gnu/release`
+pub fn profile_dirs(target_path: &Path) -> Vec<PathBuf> {
+    let subdirs = |path: &Path| -> Vec<PathBuf> {
+        path.read_dir()
+            .into_iter()
+            .flatten()
+            .filter_map(|it| it.ok())
+            .filter(|it| it.file_type().is_ok_and(|it| it.is_dir()))
+            .map(|it| it.path())
+            .collect()
+    };
+    let mut dirs = vec![];
+    for dir in subdirs(target_path) {
+        if dir.join(".fingerprint").is_dir() {
+            dirs.push(dir);
+        } else {
+            dirs.extend(
+                subdirs(&dir)
+                    .into_iter()
+                    .filter(|it| it.join(".fingerprint").is_dir()),
+            );
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+/// The compilation units of every profile directory of `target_path`
+pub fn scan_units(target_path: &Path) -> Vec<BuildUnit> {
+    profile_dirs(target_path)
+        .iter()
+        .flat_map(|it| scan_profile_units(it))
+        .collect()
+}
+
+fn scan_profile_units(profile_dir: &Path) -> Vec<BuildUnit> {
+    let mut files_by_hash = HashMap::<String, Vec<PathBuf>>::new();
+    for dir in ["deps", "build", "examples"] {
+        for entry in profile_dir
+            .join(dir)
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .filter_map(|it| it.ok())
+        {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let stem = file_name.split('.').next().unwrap_or_default();
+            if let Some((_, hash)) = split_hash(stem) {
+                files_by_hash
+                    .entry(hash.to_string())
+                    .or_default()
+                    .push(entry.path());
+            }
+        }
+    }
+
+    let mut units = vec![];
+    for entry in profile_dir
+        .join(".fingerprint")
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.ok())
+    {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some((package, hash)) = split_hash(&file_name) else {
+            continue;
+        };
+        let fingerprint_dir = entry.path();
+        let json = read_dir_entries(&fingerprint_dir)
+            .into_iter()
+            .find(|(name, _)| name.ends_with(".json"));
+        let fingerprint = json.as_ref().and_then(|(_, path)| {
+            let text = std::fs::read_to_string(path).ok()?;
+            serde_json::from_str::<Fingerprint>(&text).ok()
+        });
+        let target = json
+            .as_ref()
+            .zip(fingerprint.as_ref())
+            .map(|((name, _), it)| UnitTarget {
+                name: name.trim_end_matches(".json").to_string(),
+                target: it.target,
+                path: it.path,
+                profile: it.profile,
+                compile_kind: it.compile_kind,
+            });
+        // Cargo writes the fingerprint next to its json as hex of the little endian bytes
+        let fingerprint_hash = target.as_ref().and_then(|it| {
+            let text = read_keeping_atime(&fingerprint_dir.join(&it.name))?;
+            u64::from_str_radix(text.trim(), 16)
+                .ok()
+                .map(u64::swap_bytes)
+        });
+        let deps = fingerprint
+            .as_ref()
+            .map(|it| {
+                it.deps
+                    .iter()
+                    .filter_map(|it| it.get(3)?.as_u64())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut paths = vec![fingerprint_dir];
+        paths.extend(files_by_hash.remove(hash).unwrap_or_default());
+        // Access times only count for files we never read ourselves. Cargo reads the hash and
+        // dep-info files in the fingerprint directory on every build, even when nothing changed.
+        let fingerprint_files = read_dir_entries(&paths[0]).into_iter().map(|it| it.1);
+        let (size, last_used) = fingerprint_files
+            .chain(paths[1..].iter().cloned())
+            .map(|path| {
+                if path.extension().is_some_and(|it| it == "json" || it == "d") {
+                    ProjectTargetAnalysis::recursive_scan_target(&path)
+                } else {
+                    measure(&path)
+                }
+            })
+            .fold((0, SystemTime::UNIX_EPOCH), |a, b| {
+                (a.0 + b.0, a.1.max(b.1))
+            });
+        units.push(BuildUnit {
+            profile_dir: profile_dir.to_owned(),
+            package: package.to_string(),
+            hash: hash.to_string(),
+            rustc: fingerprint.map(|it| it.rustc),
+            target,
+            fingerprint: fingerprint_hash,
+            deps,
+            paths,
+            size,
+            last_used,
+        });
+    }
+    units
+}
+
+/// Read a file without updating its access time, which tells whether cargo still uses it
+fn read_keeping_atime(path: &Path) -> Option<String> {
+    let accessed = path.metadata().ok()?.accessed().ok()?;
+    let text = std::fs::read_to_string(path).ok()?;
+    let times = std::fs::FileTimes::new().set_accessed(accessed);
+    let _ = std::fs::File::open(path).and_then(|it| it.set_times(times));
+    Some(text)
+}
+
+/// Units superseded by another variant of the same crate, e.g. after a dependency upgrade or a
+/// change of features. The most recently used variant of every crate is current, as is every
+/// unit a current unit was built against, so variants built with different features for
+/// different dependents are kept. Their files in `deps`, including the `.d` dep-info, go with
+/// them.
+pub fn superseded_units(units: &[BuildUnit]) -> Vec<&BuildUnit> {
+    let mut newest = HashMap::<(&Path, &UnitTarget), usize>::new();
+    for (index, unit) in units.iter().enumerate() {
+        let Some(target) = &unit.target else { continue };
+        let newest = newest.entry((&unit.profile_dir, target)).or_insert(index);
+        if units[*newest].last_used < unit.last_used {
+            *newest = index;
+        }
+    }
+    // Host dependencies live in another profile directory when cross compiling
+    let by_fingerprint = units
+        .iter()
+        .enumerate()
+        .filter_map(|(index, it)| Some((it.fingerprint?, index)))
+        .collect::<HashMap<_, _>>();
+    let mut current = HashSet::new();
+    let mut pending = newest.into_values().collect::<Vec<_>>();
+    while let Some(index) = pending.pop() {
+        if current.insert(index) {
+            pending.extend(
+                units[index]
+                    .deps
+                    .iter()
+                    .filter_map(|it| by_fingerprint.get(it)),
+            );
+        }
+    }
+    units
+        .iter()
+        .enumerate()
+        .filter(|(index, it)| it.target.is_some() && !current.contains(index))
+        .map(|(_, it)| it)
+        .collect()
+}
+
+/// Units built by another compiler than the most recently built unit of their profile
+/// directory. Cargo includes the compiler in the hash, so they are never used again unless the
+/// project goes back to that compiler.
+pub fn stale_toolchain_units(units: &[BuildUnit]) -> Vec<&BuildUnit> {
+    let mut current = HashMap::<&Path, (SystemTime, u64)>::new();
+    for unit in units {
+        let Some(rustc) = unit.rustc else { continue };
+        let latest = current
+            .entry(&unit.profile_dir)
+            .or_insert((unit.last_used, rustc));
+        if unit.last_used > latest.0 {
+            *latest = (unit.last_used, rustc);
+        }
+    }
+    units
+        .iter()
+        .filter(|unit| {
+            let current = current.get(unit.profile_dir.as_path());
+            matches!((unit.rustc, current), (Some(rustc), Some((_, it))) if rustc != *it)
+        })
+        .collect()
+}
+
+/// Remove every file and directory of `units`
+pub fn remove_units<'a>(units: impl IntoIterator<Item = &'a BuildUnit>) -> anyhow::Result<()> {
+    units
+        .into_iter()
+        .flat_map(|it| &it.paths)
+        .try_for_each(|it| remove_path(it))
+}
+
+fn remove_path(path: &Path) -> anyhow::Result<()> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match result {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Incremental compilation caches of every profile directory of `target_path`, one directory
+/// per crate holding its sessions, with their size and last use
+pub fn incremental_dirs(target_path: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    profile_dirs(target_path)
+        .iter()
+        .flat_map(|it| read_dir_entries(&it.join("incremental")))
+        .map(|(_, path)| {
+            let (size, last_used) = measure(&path);
+            (path, size, last_used)
+        })
+        .collect()
+}
+
+/// What sweeping a target directory removes
+#[derive(Clone, Debug, Default)]
+pub struct SweepPlan {
+    pub units: Vec<BuildUnit>,
+    /// Incremental cache directories of crates with their size
+    pub incremental: Vec<(PathBuf, u64)>,
+}
+
+impl SweepPlan {
+    pub fn size(&self) -> u64 {
+        let units = self.units.iter().map(|it| it.size).sum::<u64>();
+        units + self.incremental.iter().map(|it| it.1).sum::<u64>()
+    }
+
+    /// Remove the planned units and incremental caches, returning the bytes removed
+    pub fn remove(&self) -> anyhow::Result<u64> {
+        remove_units(&self.units)?;
+        for (path, _) in &self.incremental {
+            remove_path(path)?;
+        }
+        Ok(self.size())
+    }
+}
+
+/// The build units and incremental caches of the target and build directories `dirs` last used
+/// before `cutoff`. Units are planned as a whole, so every crate is either kept complete or
+/// rebuilt.
+pub fn plan_sweep(dirs: &[PathBuf], cutoff: SystemTime) -> SweepPlan {
+    SweepPlan {
+        units: dirs
+            .iter()
+            .flat_map(|it| scan_units(it))
+            .filter(|it| it.last_used < cutoff)
+            .collect(),
+        incremental: dirs
+            .iter()
+            .flat_map(|it| incremental_dirs(it))
+            .filter(|it| it.2 < cutoff)
+            .map(|(path, size, _)| (path, size))
+            .collect(),
+    }
+}
+
+/// Remove the build units and incremental caches of `dirs` last used before `cutoff`. Returns
+/// the bytes removed.
+pub fn sweep(dirs: &[PathBuf], cutoff: SystemTime) -> anyhow::Result<u64> {
+    plan_sweep(dirs, cutoff).remove()
+}
+
+/// Name of the stamp file `cargo cleaner stamp` writes into the target directory
+pub const STAMP_FILE_NAME: &str = "cargo-cleaner.stamp";
+
+/// Record `time` in the stamp file of `target_path`, creating the directory if needed.
+/// Returns the path of the stamp file.
+pub fn write_stamp(target_path: &Path, time: SystemTime) -> anyhow::Result<PathBuf> {
+    let since_epoch = time.duration_since(SystemTime::UNIX_EPOCH)?;
+    std::fs::create_dir_all(target_path)
+        .with_context(|| format!("failed to create {}", target_path.display()))?;
+    let path = target_path.join(STAMP_FILE_NAME);
+    let text = format!(
+        "{}.{:09}\n",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos()
+    );
+    std::fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The time recorded in the stamp file of `target_path`
+pub fn read_stamp(target_path: &Path) -> anyhow::Result<SystemTime> {
+    let path = target_path.join(STAMP_FILE_NAME);
+    let text = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "failed to read {}, run `cargo cleaner stamp` first",
+            path.display()
+        )
+    })?;
+    let (secs, nanos) = text.trim().split_once('.').unwrap_or((text.trim(), "0"));
+    let since_epoch = secs
+        .parse()
+        .ok()
+        .zip(nanos.parse().ok())
+        .map(|(secs, nanos)| Duration::new(secs, nanos))
+        .with_context(|| format!("invalid stamp in {}", path.display()))?;
+    Ok(SystemTime::UNIX_EPOCH + since_epoch)
+}
+
+/// Directory below the target directory rust-analyzer builds into when it is configured to use
+/// its own target directory
+pub const RUST_ANALYZER_DIR: &str = "rust-analyzer";
+
+/// Artifacts of editor and lint runs, which are rebuilt cheaply
+#[derive(Clone, Debug, Default)]
+pub struct CheckArtifacts {
+    /// rust-analyzer's target directory with its size
+    pub rust_analyzer: Option<(PathBuf, u64)>,
+    /// Metadata-only units outside of rust-analyzer's target directory
+    pub units: Vec<BuildUnit>,
+}
+
+impl CheckArtifacts {
+    pub fn rust_analyzer_size(&self) -> u64 {
+        self.rust_analyzer.as_ref().map_or(0, |it| it.1)
+    }
+
+    pub fn check_only_size(&self) -> u64 {
+        self.units.iter().map(|it| it.size).sum()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.rust_analyzer_size() + self.check_only_size()
+    }
+
+    /// Remove rust-analyzer's target directory and the metadata-only units
+    pub fn remove(&self) -> anyhow::Result<()> {
+        if let Some((path, _)) = &self.rust_analyzer {
+            remove_path(path)?;
+        }
+        remove_units(&self.units)
+    }
+}
+
+/// The rust-analyzer target directory of `target_path` and its metadata-only `units`
+pub fn check_artifacts(target_path: &Path, units: &[BuildUnit]) -> CheckArtifacts {
+    let rust_analyzer = target_path.join(RUST_ANALYZER_DIR);
+    CheckArtifacts {
+        units: units
+            .iter()
+            .filter(|it| !it.profile_dir.starts_with(&rust_analyzer) && it.is_check_only())
+            .cloned()
+            .collect(),
+        rust_analyzer: Some(rust_analyzer).filter(|it| it.is_dir()).map(|it| {
+            let (size, _) = ProjectTargetAnalysis::recursive_scan_target(&it);
+            (it, size)
+        }),
+    }
+}
+
+/// Space one crate takes in a target directory, summed over all its build units
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrateSize {
+    /// Package name, or [`OTHER_FILES`] for everything not belonging to a crate
+    pub name: String,
+    pub size: u64,
+    /// Number of build units, one per profile, target kind and variant
+    pub units: usize,
+    /// Part of the size in `build`, the compiled build scripts and their `OUT_DIR`s
+    pub build_script_size: u64,
+    /// Part of the size in incremental compilation caches
+    pub incremental_size: u64,
+    /// Part of the size from `cargo check`, clippy and rust-analyzer, see [`check_artifacts`]
+    pub check_size: u64,
+}
+
+/// Name of the [`CrateSize`] of files outside of build units, like uplifted binaries
+pub const OTHER_FILES: &str = "(other)";
+
+/// Sizes per crate of the target and build directories `dirs`, largest first: the build `units`
+/// scanned from them and the incremental caches of every profile
+pub fn crate_sizes(dirs: &[PathBuf], units: &[BuildUnit]) -> Vec<CrateSize> {
+    let rust_analyzer_dirs = dirs
+        .iter()
+        .map(|it| it.join(RUST_ANALYZER_DIR))
+        .collect::<Vec<_>>();
+    let mut crates = HashMap::<String, CrateSize>::new();
+    for unit in units {
+        let build_dir = unit.profile_dir.join("build");
+        let build_script_size = unit
+            .paths
+            .iter()
+            .filter(|it| it.starts_with(&build_dir))
+            .map(|it| ProjectTargetAnalysis::recursive_scan_target(it).0)
+            .sum::<u64>();
+        let check = rust_analyzer_dirs
+            .iter()
+            .any(|it| unit.profile_dir.starts_with(it))
+            || unit.is_check_only();
+        let entry = crates.entry(unit.package.clone()).or_default();
+        entry.size += unit.size;
+        entry.units += 1;
+        entry.build_script_size += build_script_size;
+        if check {
+            entry.check_size += unit.size;
+        }
+    }
+    // Incremental caches are named after the crate, which uses `_` where the package has `-`
+    let packages = crates
+        .keys()
+        .map(|it| (it.replace('-', "_"), it.clone()))
+        .collect::<HashMap<_, _>>();
+    for (path, size, _) in dirs.iter().flat_map(|it| incremental_dirs(it)) {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let crate_name = file_name
+            .rsplit_once('-')
+            .map_or(file_name.as_ref(), |it| it.0);
+        let name = packages
+            .get(crate_name)
+            .cloned()
+            .unwrap_or_else(|| crate_name.to_string());
+        let entry = crates.entry(name).or_default();
+        entry.size += size;
+        entry.incremental_size += size;
+    }
+
+    let total = dirs
+        .iter()
+        .map(|it| ProjectTargetAnalysis::recursive_scan_target(it).0)
+        .sum::<u64>();
+    let other = total.saturating_sub(crates.values().map(|it| it.size).sum());
+    let mut crates = crates
+        .into_iter()
+        .map(|(name, it)| CrateSize { name, ..it })
+        .collect::<Vec<_>>();
+    if other > 0 {
+        crates.push(CrateSize {
+            name: OTHER_FILES.to_string(),
+            size: other,
+            ..Default::default()
+        });
+    }
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    crates
+}
+
+/// Version of the compiler that last built into `target_path` as cargo recorded it in
+/// `.rustc_info.json`, e.g. `1.84.0 (9fc6b4312 2025-01-07)`
+pub fn read_rustc_version(target_path: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(target_path.join(".rustc_info.json")).ok()?;
+    let info = serde_json::from_str::<RustcInfo>(&text).ok()?;
+    info.outputs
+        .values()
+        .find_map(|it| it.stdout.lines().next()?.strip_prefix("rustc "))
+        .map(str::to_string)
+}
+
+/// Versions of the installed compilers, read from the channel manifests of rustup's toolchains
+/// and from `rustc -V` for compilers installed otherwise. Looked up once per run.
+pub fn installed_compilers() -> &'static HashSet<String> {
+    static INSTALLED: OnceLock<HashSet<String>> = OnceLock::new();
+    INSTALLED.get_or_init(|| {
+        let version = Regex::new(r#"(?m)^\[pkg\.rustc\]\s*\nversion = "([^"]+)""#).unwrap();
+        let mut installed = rustup_home()
+            .map(|it| it.join("toolchains"))
+            .and_then(|it| it.read_dir().ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|it| it.ok())
+            .filter_map(|it| {
+                let manifest = it
+                    .path()
+                    .join("lib/rustlib/multirust-channel-manifest.toml");
+                let text = std::fs::read_to_string(manifest).ok()?;
+                Some(version.captures(&text)?[1].to_string())
+            })
+            .collect::<HashSet<_>>();
+        let rustc = Command::new("rustc").arg("-V").output();
+        if let Some(output) = rustc.ok().filter(|it| it.status.success()) {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            installed.extend(stdout.trim().strip_prefix("rustc ").map(str::to_string));
+        }
+        installed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn set_times(path: &Path, time: SystemTime) {
+        let times = std::fs::FileTimes::new()
+            .set_accessed(time)
+            .set_modified(time);
+        std::fs::File::open(path).unwrap().set_times(times).unwrap();
+    }
+
+    fn write_unit(profile: &Path, package: &str, hash: &str, rustc: u64, age: Duration) {
+        let fingerprint = profile
+            .join(".fingerprint")
+            .join(format!("{package}-{hash}"));
+        std::fs::create_dir_all(&fingerprint).unwrap();
+        let json = fingerprint.join(format!("lib-{package}.json"));
+        std::fs::write(&json, format!(r#"{{"rustc":{rustc},"features":"[]"}}"#)).unwrap();
+        let rlib = profile
+            .join("deps")
+            .join(format!("lib{package}-{hash}.rlib"));
+        std::fs::write(&rlib, [0; 100]).unwrap();
+        let time = SystemTime::now() - age;
+        for path in [json, rlib] {
+            set_times(&path, time);
+        }
+    }
+
+    /// Add a fingerprint hash and dependencies to a unit written by [`write_unit`]
+    fn write_fingerprint(
+        profile: &Path,
+        package: &str,
+        hash: &str,
+        fingerprint: u64,
+        deps: &[u64],
+    ) {
+        let dir = profile
+            .join(".fingerprint")
+            .join(format!("{package}-{hash}"));
+        let json = dir.join(format!("lib-{package}.json"));
+        let times = json.metadata().unwrap();
+        let deps = deps
+            .iter()
+            .map(|it| format!(r#"[1,"dep",false,{it}]"#))
+            .collect::<Vec<_>>()
+            .join(",");
+        std::fs::write(
+            &json,
+            format!(r#"{{"rustc":1,"target":2,"path":3,"profile":4,"deps":[{deps}]}}"#),
+        )
+        .unwrap();
+        let hash_file = dir.join(format!("lib-{package}"));
+        let le_hex = fingerprint
+            .to_le_bytes()
+            .map(|it| format!("{it:02x}"))
+            .concat();
+        std::fs::write(&hash_file, le_hex).unwrap();
+        for path in [json, hash_file] {
+            set_times(&path, times.modified().unwrap());
+        }
+    }
+
+    #[test]
+    fn units_of_older_compilers_are_stale() {
+        let target = TempDir::new("units");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        std::fs::write(
+            target.join(".rustc_info.json"),
+            r#"{"rustc_fingerprint":1,"outputs":{"2":{"success":true,"status":"","code":0,"stdout":"rustc 1.84.0 (9fc6b4312 2025-01-07)\nbinary: rustc\n","stderr":""}},"successes":{}}"#,
+        )
+        .unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        write_unit(&debug, "serde", "0123456789abcdef", 1, day * 30);
+        write_unit(&debug, "serde", "fedcba9876543210", 2, day);
+        write_unit(&debug, "libc", "00112233aabbccdd", 2, Duration::ZERO);
+
+        assert_eq!(profile_dirs(&target), vec![debug.clone()]);
+        let units = scan_units(&target);
+        assert_eq!(units.len(), 3);
+        assert!(units.iter().all(|it| it.paths.len() == 2 && it.size > 100));
+        let stale = stale_toolchain_units(&units);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].hash, "0123456789abcdef");
+        assert_eq!(
+            read_rustc_version(&target).as_deref(),
+            Some("1.84.0 (9fc6b4312 2025-01-07)")
+        );
+
+        remove_units(stale).unwrap();
+        assert!(!debug.join("deps/libserde-0123456789abcdef.rlib").exists());
+        assert_eq!(scan_units(&target).len(), 2);
+    }
+
+    #[test]
+    fn sweep_removes_whole_units() {
+        let target = TempDir::new("sweep");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        write_unit(&debug, "old", "0123456789abcdef", 1, day * 30);
+        write_unit(&debug, "fresh", "fedcba9876543210", 1, day);
+        let session = debug.join("incremental/old-1x2y3z/s-abc");
+        std::fs::create_dir_all(&session).unwrap();
+        std::fs::write(session.join("query-cache.bin"), [0; 10]).unwrap();
+        for path in [
+            &session.join("query-cache.bin"),
+            &session,
+            session.parent().unwrap(),
+        ] {
+            set_times(path, SystemTime::now() - day * 30);
+        }
+
+        let removed = sweep(&[target.to_path_buf()], SystemTime::now() - day * 7).unwrap();
+        assert!(removed > 110);
+        let units = scan_units(&target);
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].package, "fresh");
+        assert!(!debug.join("deps/libold-0123456789abcdef.rlib").exists());
+        assert!(!debug.join("incremental/old-1x2y3z").exists());
+    }
+
+    #[test]
+    fn sweep_since_stamp_keeps_units_cargo_read() {
+        let target = TempDir::new("stamp");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        write_unit(&debug, "unused", "0123456789abcdef", 1, day);
+        write_unit(&debug, "fresh", "fedcba9876543210", 1, day);
+        let stamp = SystemTime::now() - day / 2;
+        write_stamp(&target, stamp).unwrap();
+        assert_eq!(read_stamp(&target).unwrap(), stamp);
+
+        // A build where nothing changed only reads the fingerprint hash of a unit
+        let hash = debug.join(".fingerprint/fresh-fedcba9876543210/lib-fresh");
+        std::fs::write(&hash, "0123456789abcdef").unwrap();
+        let times = std::fs::FileTimes::new()
+            .set_accessed(SystemTime::now())
+            .set_modified(SystemTime::now() - day);
+        std::fs::File::open(&hash)
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+
+        let plan = plan_sweep(&[target.to_path_buf()], read_stamp(&target).unwrap());
+        assert_eq!(plan.units.len(), 1);
+        assert_eq!(plan.units[0].package, "unused");
+        assert_eq!(plan.remove().unwrap(), plan.size());
+        assert_eq!(scan_units(&target).len(), 1);
+        assert!(target.join(STAMP_FILE_NAME).is_file());
+    }
+
+    #[test]
+    fn superseded_variants_are_found() {
+        let target = TempDir::new("dedupe");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        // An upgrade left an old variant of `dep`, another variant with other features is still
+        // used by `app`
+        write_unit(&debug, "dep", "0000000000000001", 1, day * 10);
+        write_fingerprint(&debug, "dep", "0000000000000001", 11, &[]);
+        write_unit(&debug, "dep", "0000000000000002", 1, day * 5);
+        write_fingerprint(&debug, "dep", "0000000000000002", 12, &[]);
+        write_unit(&debug, "dep", "0000000000000003", 1, day);
+        write_fingerprint(&debug, "dep", "0000000000000003", 13, &[]);
+        write_unit(&debug, "app", "00000000000000aa", 1, Duration::ZERO);
+        write_fingerprint(&debug, "app", "00000000000000aa", 21, &[12, 13]);
+
+        let units = scan_units(&target);
+        assert_eq!(
+            units.iter().find(|it| it.package == "app").unwrap().deps,
+            vec![12, 13]
+        );
+        let superseded = superseded_units(&units);
+        assert_eq!(superseded.len(), 1);
+        assert_eq!(superseded[0].hash, "0000000000000001");
+    }
+
+    #[test]
+    fn sizes_are_summed_per_crate() {
+        let target = TempDir::new("crates");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        write_unit(&debug, "ring", "0000000000000001", 1, Duration::ZERO);
+        write_unit(&debug, "ring", "0000000000000002", 1, Duration::ZERO);
+        let out_dir = debug.join("build/ring-0000000000000002/out");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        std::fs::write(out_dir.join("libring_core.a"), [0; 1000]).unwrap();
+        write_unit(&debug, "my-app", "00000000000000aa", 1, Duration::ZERO);
+        let session = debug.join("incremental/my_app-1x2y3z/s-abc");
+        std::fs::create_dir_all(&session).unwrap();
+        std::fs::write(session.join("query-cache.bin"), [0; 10]).unwrap();
+        std::fs::write(debug.join("my-app"), [0; 5]).unwrap();
+
+        let crates = crate_sizes(&[target.to_path_buf()], &scan_units(&target));
+        let names = crates.iter().map(|it| it.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["ring", "my-app", OTHER_FILES]);
+        assert_eq!(crates[0].units, 2);
+        assert_eq!(crates[0].build_script_size, 1000);
+        assert_eq!(crates[1].incremental_size, 10);
+        assert_eq!(crates[2].size, 5);
+    }
+
+    #[test]
+    fn check_artifacts_are_found() {
+        let target = TempDir::new("check");
+        let debug = target.join("debug");
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        write_unit(&debug, "built", "0000000000000001", 1, Duration::ZERO);
+        write_unit(&debug, "checked", "0000000000000002", 1, Duration::ZERO);
+        std::fs::rename(
+            debug.join("deps/libchecked-0000000000000002.rlib"),
+            debug.join("deps/libchecked-0000000000000002.rmeta"),
+        )
+        .unwrap();
+        std::fs::write(debug.join("deps/checked-0000000000000002.d"), "").unwrap();
+        let analyzer = target.join(RUST_ANALYZER_DIR).join("debug");
+        std::fs::create_dir_all(analyzer.join("deps")).unwrap();
+        write_unit(&analyzer, "built", "0000000000000003", 1, Duration::ZERO);
+
+        let units = scan_units(&target);
+        assert_eq!(units.len(), 3);
+        let check = check_artifacts(&target, &units);
+        assert_eq!(check.units.len(), 1);
+        assert_eq!(check.units[0].package, "checked");
+        assert!(check.rust_analyzer_size() > 100);
+        let crates = crate_sizes(&[target.to_path_buf()], &units);
+        let built = crates.iter().find(|it| it.name == "built").unwrap();
+        assert_eq!(built.check_size, check.rust_analyzer_size());
+
+        check.remove().unwrap();
+        assert!(!target.join(RUST_ANALYZER_DIR).exists());
+        let units = scan_units(&target);
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].package, "built");
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::cargo_home::{measure, read_dir_entries, CacheEntry, CachePart, PartKind};
+use crate::lockfile::LockedPackage;
+
+/// Version used for the entry holding the bare database of a repository
+pub const DATABASE_VERSION: &str = "(database)";
+
+/// Repository name of a `<name>-<hash>` directory below `git/db` or `git/checkouts`
+fn repository_name(dir_name: &str) -> &str {
+    dir_name
+        .rsplit_once('-')
+        .filter(|(_, hash)| hash.len() == 16 && hash.chars().all(|it| it.is_ascii_hexdigit()))
+        .map_or(dir_name, |(name, _)| name)
+}
+
+/// Repository name and locked revision of a `git+<url>[?<query>]#<revision>` lockfile source,
+/// named like the directories of the git cache
+pub fn parse_git_source(source: &str) -> Option<(String, &str)> {
+    let (url, revision) = source.strip_prefix("git+")?.split_once('#')?;
+    let path = url.split(['?', '#']).next()?.trim_end_matches('/');
+    let name = path.rsplit('/').next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    Some((name.to_lowercase(), revision))
+}
+
+/// Analyze `$CARGO_HOME/git`: one entry per bare repository database and one per checked out
+/// revision, largest first
+pub fn scan_git(cargo_home: &Path) -> Vec<CacheEntry> {
+    let git = cargo_home.join("git");
+    let mut entries = vec![];
+
+    for (group, path) in read_dir_entries(&git.join("db")) {
+        let (size, last_used) = measure(&path);
+        entries.push(CacheEntry {
+            id: Uuid::new_v4(),
+            name: repository_name(&group).to_string(),
+            group,
+            version: DATABASE_VERSION.to_string(),
+            parts: vec![CachePart {
+                kind: PartKind::GitDatabase,
+                path,
+                size,
+            }],
+            last_used,
+            in_use: None,
+            protected: false,
+        });
+    }
+
+    for (group, group_path) in read_dir_entries(&git.join("checkouts")) {
+        for (revision, path) in read_dir_entries(&group_path) {
+            let (size, last_used) = measure(&path);
+            entries.push(CacheEntry {
+                id: Uuid::new_v4(),
+                group: group.clone(),
+                name: repository_name(&group).to_string(),
+                version: revision,
+                parts: vec![CachePart {
+                    kind: PartKind::GitCheckout,
+                    path,
+                    size,
+                }],
+                last_used,
+                in_use: None,
+                protected: false,
+            });
+        }
+    }
+
+    entries.sort_by_key(|it| std::cmp::Reverse(it.parts.iter().map(|it| it.size).sum::<u64>()));
+    entries
+}
+
+/// Mark checkouts as used when a locked git package of the same repository is at their revision,
+/// and databases when any package of the repository is locked. Repositories are told apart by
+/// name only, so same-named repositories of different hosts keep each other.
+pub fn mark_in_use(entries: &mut [CacheEntry], packages: &HashSet<LockedPackage>) {
+    let locked = packages
+        .iter()
+        .filter_map(|it| parse_git_source(it.source.as_deref()?))
+        .collect::<Vec<_>>();
+    for entry in entries.iter_mut() {
+        let name = entry.name.to_lowercase();
+        let in_use = locked.iter().any(|(locked_name, revision)| {
+            *locked_name == name
+                && (entry.version == DATABASE_VERSION || revision.starts_with(&entry.version))
+        });
+        entry.in_use = Some(in_use);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn lockfile_sources_are_parsed() {
+        assert_eq!(
+            parse_git_source("git+https://github.com/Foo/bar.git?branch=main#0123abcd"),
+            Some(("bar".to_string(), "0123abcd"))
+        );
+        assert_eq!(
+            parse_git_source("git+ssh://git@example.com/baz#ffee"),
+            Some(("baz".to_string(), "ffee"))
+        );
+        assert_eq!(
+            parse_git_source("registry+https://github.com/rust-lang/crates.io-index"),
+            None
+        );
+        assert_eq!(repository_name("bar-4f2c1a0e9b8d7c6a"), "bar");
+        assert_eq!(repository_name("my-repo-4f2c1a0e9b8d7c6a"), "my-repo");
+    }
+
+    #[test]
+    fn checkouts_are_matched_by_revision() {
+        let home = TempDir::new("git");
+        let git = home.join("git");
+        std::fs::create_dir_all(git.join("db").join("bar-4f2c1a0e9b8d7c6a")).unwrap();
+        for revision in ["0123abc", "9876fed"] {
+            std::fs::create_dir_all(
+                git.join("checkouts")
+                    .join("bar-4f2c1a0e9b8d7c6a")
+                    .join(revision),
+            )
+            .unwrap();
+        }
+        let locked = LockedPackage {
+            name: "bar".to_string(),
+            version: "0.1.0".to_string(),
+            source: Some("git+https://github.com/foo/bar#0123abcdef".to_string()),
+        };
+
+        let mut entries = scan_git(&home);
+        mark_in_use(&mut entries, &HashSet::from([locked]));
+        let in_use = |version: &str| {
+            entries
+                .iter()
+                .find(|it| it.version == version)
+                .and_then(|it| it.in_use)
+        };
+        assert_eq!(entries.len(), 3);
+        assert_eq!(in_use(DATABASE_VERSION), Some(true));
+        assert_eq!(in_use("0123abc"), Some(true));
+        assert_eq!(in_use("9876fed"), Some(false));
+    }
+}
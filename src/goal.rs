@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::disk::DiskSpace;
+use crate::ProjectTargetAnalysis;
+
+const SECONDS_PER_DAY: f64 = 24.0 * 60.0 * 60.0;
+
+/// How good a candidate `project` freeing `size` bytes is for cleanup, higher is better.
+///
+/// The score is the size multiplied by the days since the target was last modified (plus one so
+/// fresh targets still count by size), i.e. "byte-days" of unused build output.
+pub fn staleness_score(project: &ProjectTargetAnalysis, size: u64, now: SystemTime) -> f64 {
+    let idle_days = now
+        .duration_since(project.last_modified)
+        .map(|it| it.as_secs_f64() / SECONDS_PER_DAY)
+        .unwrap_or(0.0);
+    size as f64 * (idle_days + 1.0)
+}
+
+/// Projects chosen to reach a free-space goal
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GoalPlan {
+    pub goal: u64,
+    /// Chosen projects, stalest first
+    pub selected: Vec<Uuid>,
+    /// Bytes freed by cleaning each chosen project
+    pub sizes: HashMap<Uuid, u64>,
+    /// Bytes freed by cleaning all chosen projects
+    pub reclaimed: u64,
+}
+
+impl GoalPlan {
+    pub fn reached(&self) -> bool {
+        self.reclaimed >= self.goal
+    }
+}
+
+/// Pick the projects to clean to free at least `goal` bytes, cleaning a project frees `size` of
+/// it, e.g. what the clean scope removes.
+///
+/// Candidates are taken stalest first until the goal is reached, afterwards the chosen projects
+/// that are not needed to stay above the goal are dropped again, least stale first. When the
+/// goal is unreachable every candidate freeing anything is chosen.
+pub fn plan_free_space<'a>(
+    projects: impl IntoIterator<Item = &'a ProjectTargetAnalysis>,
+    goal: u64,
+    now: SystemTime,
+    size: impl Fn(&ProjectTargetAnalysis) -> u64,
+) -> GoalPlan {
+    let mut ranked = projects
+        .into_iter()
+        .filter(|it| it.size > 0)
+        .map(|it| (it, size(it)))
+        .filter(|(_, size)| *size > 0)
+        .map(|(it, size)| (staleness_score(it, size, now), it, size))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let mut chosen = vec![];
+    let mut reclaimed = 0;
+    for (_, project, size) in ranked {
+        if reclaimed >= goal {
+            break;
+        }
+        reclaimed += size;
+        chosen.push((project, size));
+    }
+
+    // A big stale project picked late can make earlier small picks unnecessary
+    let mut index = chosen.len();
+    while index > 0 {
+        index -= 1;
+        if reclaimed - chosen[index].1 >= goal {
+            reclaimed -= chosen[index].1;
+            chosen.remove(index);
+        }
+    }
+
+    GoalPlan {
+        goal,
+        selected: chosen.iter().map(|(it, _)| it.id).collect(),
+        sizes: chosen.iter().map(|(it, size)| (it.id, *size)).collect(),
+        reclaimed,
+    }
+}
+
+/// Pick the projects to clean so that every filesystem holding projects has at least
+/// `min_free` bytes available afterwards, cleaning a project frees `size` of it.
+///
+/// The goal of the returned plan is the sum of the shortfalls of all filesystems.
+pub fn plan_min_free(
+    projects: &[ProjectTargetAnalysis],
+    min_free: u64,
+    disk_space: &mut DiskSpace,
+    now: SystemTime,
+    size: impl Fn(&ProjectTargetAnalysis) -> u64,
+) -> GoalPlan {
+    let mut by_device = HashMap::<u64, (u64, Vec<&ProjectTargetAnalysis>)>::new();
+    for project in projects {
+        if let Some(usage) = disk_space.usage_of(project) {
+            by_device
+                .entry(usage.device)
+                .or_insert_with(|| (usage.free, vec![]))
+                .1
+                .push(project);
+        }
+    }
+
+    let mut plan = GoalPlan::default();
+    for (free, projects) in by_device.into_values() {
+        let shortfall = min_free.saturating_sub(free);
+        let device_plan = plan_free_space(projects, shortfall, now, &size);
+        plan.goal += device_plan.goal;
+        plan.reclaimed += device_plan.reclaimed;
+        plan.selected.extend(device_plan.selected);
+        plan.sizes.extend(device_plan.sizes);
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn project(size: u64, idle_days: u32, now: SystemTime) -> ProjectTargetAnalysis {
+        ProjectTargetAnalysis {
+            id: Uuid::new_v4(),
+            project_path: PathBuf::from("/p"),
+            project_name: None,
+            target_path: PathBuf::from("/p/target"),
+            build_dir: None,
+            size,
+            allocated_size: 0,
+            last_modified: now - DAY * idle_days,
+            selected_for_cleanup: false,
+            built_with: None,
+            compiler_installed: None,
+            unit_sizes: None,
+            orphaned: false,
+            git_branch: None,
+            toolchain: None,
+            workspace_members: None,
+        }
+    }
+
+    #[test]
+    fn prefers_stale_projects_and_drops_unneeded_picks() {
+        let now = SystemTime::now();
+        let projects = [
+            project(10, 100, now), // score 1010
+            project(50, 30, now),  // score 1550
+            project(80, 0, now),   // score 80
+            project(100, 20, now), // score 2100
+        ];
+
+        let plan = plan_free_space(&projects, 120, now, |it| it.size);
+        // 100 + 50 reach the goal, the 10 byte project is never needed
+        assert_eq!(plan.selected, vec![projects[3].id, projects[1].id]);
+        assert_eq!(plan.reclaimed, 150);
+        assert!(plan.reached());
+
+        let plan = plan_free_space(&projects, 90, now, |it| it.size);
+        assert_eq!(plan.selected, vec![projects[3].id]);
+    }
+
+    #[test]
+    fn unreachable_goal_selects_everything() {
+        let now = SystemTime::now();
+        let projects = [project(10, 1, now), project(0, 5, now)];
+
+        let plan = plan_free_space(&projects, 1_000, now, |it| it.size);
+        assert_eq!(plan.selected, vec![projects[0].id]);
+        assert!(!plan.reached());
+    }
+
+    #[test]
+    fn plans_with_what_the_scope_frees() {
+        let now = SystemTime::now();
+        let projects = [project(100, 10, now), project(100, 1, now)];
+
+        // Only a tenth of the stalest target is removed, both are needed to free 100 bytes
+        let partly = |it: &ProjectTargetAnalysis| match it.id == projects[0].id {
+            true => 10,
+            false => 90,
+        };
+        let plan = plan_free_space(&projects, 100, now, partly);
+        assert_eq!(plan.selected.len(), 2);
+        assert_eq!(plan.reclaimed, 100);
+        assert_eq!(plan.sizes[&projects[0].id], 10);
+
+        let plan = plan_free_space(&projects, 100, now, |it| it.size);
+        assert_eq!(plan.selected, vec![projects[0].id]);
+    }
+}
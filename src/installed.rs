@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::cargo_home::{measure, remove_entry, CacheEntry, CachePart, CacheRemoval, PartKind};
+
+/// File cargo records `cargo install`ed packages in, with their binaries and build settings
+const CRATES2_JSON: &str = ".crates2.json";
+/// Older tracking file cargo still keeps in sync, mapping package ids to binaries
+const CRATES_TOML: &str = ".crates.toml";
+
+#[derive(Deserialize)]
+struct Crates2 {
+    #[serde(default)]
+    installs: BTreeMap<String, Install>,
+}
+
+#[derive(Deserialize)]
+struct Install {
+    #[serde(default)]
+    bins: Vec<String>,
+}
+
+/// Name and version of a `<name> <version> (<source>)` package id
+fn parse_package_id(id: &str) -> Option<(&str, &str)> {
+    let mut words = id.split(' ');
+    Some((words.next()?, words.next()?))
+}
+
+/// Analyze the packages installed by `cargo install`: one entry per package holding its
+/// binaries, largest first. No project depends on an installed tool, so every entry counts as
+/// unused once its binaries were not run for long enough.
+pub fn scan_installed(cargo_home: &Path) -> Vec<CacheEntry> {
+    let Ok(text) = std::fs::read_to_string(cargo_home.join(CRATES2_JSON)) else {
+        return vec![];
+    };
+    let Ok(crates2) = serde_json::from_str::<Crates2>(&text) else {
+        return vec![];
+    };
+    let bin = cargo_home.join("bin");
+
+    let mut entries = crates2
+        .installs
+        .iter()
+        .filter_map(|(id, install)| {
+            let (name, version) = parse_package_id(id)?;
+            let parts = install
+                .bins
+                .iter()
+                .map(|it| {
+                    let path = bin.join(format!("{it}{}", std::env::consts::EXE_SUFFIX));
+                    let (size, last_used) = measure(&path);
+                    (
+                        CachePart {
+                            kind: PartKind::Binary,
+                            path,
+                            size,
+                        },
+                        last_used,
+                    )
+                })
+                .collect::<Vec<_>>();
+            Some(CacheEntry {
+                id: Uuid::new_v4(),
+                group: install.bins.join(" "),
+                name: name.to_string(),
+                version: version.to_string(),
+                last_used: parts
+                    .iter()
+                    .map(|(_, last_used)| *last_used)
+                    .max()
+                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
+                parts: parts.into_iter().map(|(part, _)| part).collect(),
+                in_use: Some(false),
+                protected: false,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by_key(|it| std::cmp::Reverse(it.parts.iter().map(|it| it.size).sum::<u64>()));
+    entries
+}
+
+/// Uninstall the package of `entry` like `cargo uninstall`: remove its binaries, then forget it
+/// in both tracking files. Returns the bytes freed.
+pub fn uninstall(cargo_home: &Path, entry: &CacheEntry) -> anyhow::Result<u64> {
+    let freed = remove_entry(entry, CacheRemoval::All)?;
+    let is_entry =
+        |id: &str| parse_package_id(id) == Some((entry.name.as_str(), entry.version.as_str()));
+
+    let path = cargo_home.join(CRATES2_JSON);
+    if let Ok(text) = std::fs::read_to_string(&path) {
+        let mut crates2: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if let Some(installs) = crates2
+            .get_mut("installs")
+            .and_then(|it| it.as_object_mut())
+        {
+            installs.retain(|id, _| !is_entry(id));
+        }
+        std::fs::write(&path, serde_json::to_string(&crates2)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    let path = cargo_home.join(CRATES_TOML);
+    if let Ok(text) = std::fs::read_to_string(&path) {
+        let mut crates: toml::Table =
+            toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+        if let Some(v1) = crates.get_mut("v1").and_then(|it| it.as_table_mut()) {
+            v1.retain(|id, _| !is_entry(id));
+        }
+        std::fs::write(&path, toml::to_string(&crates)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn uninstall_forgets_the_package() {
+        let home = TempDir::new("bin");
+        let bin = home.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let suffix = std::env::consts::EXE_SUFFIX;
+        std::fs::write(bin.join(format!("rg{suffix}")), [0; 100]).unwrap();
+        std::fs::write(bin.join(format!("foo{suffix}")), [0; 10]).unwrap();
+        let ripgrep = "ripgrep 14.1.0 (registry+https://github.com/rust-lang/crates.io-index)";
+        let foo = "foo-cli 0.1.0 (path+file:///src/foo)";
+        std::fs::write(
+            home.join(CRATES2_JSON),
+            serde_json::json!({
+                "installs": {
+                    ripgrep: { "bins": ["rg"], "profile": "release" },
+                    foo: { "bins": ["foo"], "profile": "release" },
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            home.join(CRATES_TOML),
+            format!("[v1]\n\"{ripgrep}\" = [\"rg\"]\n\"{foo}\" = [\"foo\"]\n"),
+        )
+        .unwrap();
+
+        let entries = scan_installed(&home);
+        assert_eq!(entries.len(), 2);
+        let rg = &entries[0];
+        assert_eq!(
+            (rg.group.as_str(), rg.name.as_str(), rg.version.as_str()),
+            ("rg", "ripgrep", "14.1.0")
+        );
+        assert_eq!(rg.size(CacheRemoval::default()), 100);
+
+        assert_eq!(uninstall(&home, rg).unwrap(), 100);
+        assert!(!bin.join(format!("rg{suffix}")).exists());
+        let entries = scan_installed(&home);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "foo-cli");
+        let crates = std::fs::read_to_string(home.join(CRATES_TOML)).unwrap();
+        assert!(!crates.contains("ripgrep") && crates.contains("foo-cli"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Context;
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::units::format_timestamp;
+
+/// One cleanup performed by cargo cleaner, appended as a JSON line to the journal
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// RFC 3339 timestamp of the cleanup
+    pub time: String,
+    /// What triggered the cleanup, e.g. `tui`, `clean` or `daemon`
+    pub source: String,
+    pub project_path: PathBuf,
+    /// What was removed, e.g. the clean scope
+    pub action: String,
+    /// Bytes freed, 0 when the cleanup failed
+    pub freed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(
+        source: &str,
+        project_path: &Path,
+        action: &str,
+        result: &anyhow::Result<u64>,
+    ) -> Self {
+        Self {
+            time: format_timestamp(SystemTime::now()),
+            source: source.to_string(),
+            project_path: project_path.to_owned(),
+            action: action.to_string(),
+            freed: *result.as_ref().unwrap_or(&0),
+            error: result.as_ref().err().map(|it| format!("{it:#}")),
+        }
+    }
+}
+
+/// Location of the journal, `$XDG_STATE_HOME/cargo-cleaner/journal.jsonl` falling back to
+/// `~/.local/state/cargo-cleaner/journal.jsonl`
+pub fn journal_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|it| it.is_absolute())
+        .or_else(|| home_dir().map(|it| it.join(".local").join("state")))
+        .map(|it| it.join("cargo-cleaner").join("journal.jsonl"))
+}
+
+/// Append `entry` to the journal at `path`, creating it if needed
+pub fn append_to(path: &Path, entry: &JournalEntry) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open journal {}", path.display()))?;
+    // A single write keeps lines of concurrent writers from interleaving
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Append `entry` to the default journal. Journal failures never stop a cleanup, so errors are
+/// only reported to the caller for logging.
+pub fn append(entry: &JournalEntry) -> anyhow::Result<()> {
+    let path = journal_path().context("can not determine the journal location")?;
+    append_to(&path, entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn entries_are_appended_as_json_lines() {
+        let dir = TempDir::new("journal");
+        let path = dir.join("journal.jsonl");
+        let ok = JournalEntry::new("clean", Path::new("/a"), "all", &Ok(42));
+        let failed = JournalEntry::new(
+            "daemon",
+            Path::new("/b"),
+            "all",
+            &Err(anyhow::anyhow!("boom")),
+        );
+        append_to(&path, &ok).unwrap();
+        append_to(&path, &failed).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let entries = text
+            .lines()
+            .map(|it| serde_json::from_str::<JournalEntry>(it).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![ok, failed]);
+        assert_eq!(entries[1].error.as_deref(), Some("boom"));
+    }
+}
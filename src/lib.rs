@@ -1,13 +1,46 @@
+pub mod breakdown;
+pub mod cache_tab;
+pub mod cargo_config;
+pub mod cargo_home;
+pub mod clean;
+pub mod columns;
+pub mod config;
+pub mod daemon;
+pub mod disk;
+pub mod filter;
+pub mod fingerprint;
+pub mod git_cache;
+pub mod goal;
+pub mod installed;
+pub mod journal;
+pub mod list;
+pub mod lockfile;
 pub mod notify_rw_lock;
+pub mod registry;
+pub mod schedule;
+pub mod search;
+pub mod sort;
+#[cfg(test)]
+mod test_support;
+pub mod toolchain;
+pub mod tree;
 pub mod tui;
 pub mod tui_app;
+pub mod units;
+pub mod vcs;
 
 /// Size of one gibibyte (GiB) in bytes
 pub const GIB_SIZE: u64 = 1024 * 1024 * 1024;
 
+/// Shown in place of the project name of a target directory without a project
+pub const ORPHANED_TARGET: &str = "(no manifest found)";
+
+use crate::cargo_config::{resolve_build_dir, resolve_target_dir};
+use crate::columns::Column;
 use crate::notify_rw_lock::{NotifyRwLock, NotifySender};
 use cargo_toml::Manifest;
 use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -17,16 +50,45 @@ use uuid::Uuid;
 /// new jobs for recursively searching the dirs
 struct Job(PathBuf, Sender<Job>);
 
+/// Directories the project finder does not descend into.
+///
+/// Entries containing a `/` are matched as absolute paths (the directory and everything below
+/// it), all other entries match directories with that name anywhere in the tree.
+#[derive(Clone, Debug, Default)]
+pub struct Excludes {
+    names: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl Excludes {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let (paths, names): (Vec<_>, Vec<_>) = patterns
+            .iter()
+            .map(|it| it.as_ref())
+            .partition(|it| it.contains('/'));
+        Self {
+            names: names.into_iter().map(str::to_owned).collect(),
+            paths: paths.into_iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.names.iter().any(|it| *it == name) || self.paths.iter().any(|it| path.starts_with(it))
+    }
+}
+
 pub struct Progress {
     pub total: usize,
     pub scanned: usize,
 }
 
-/// Recursively scan the given path for cargo projects using the specified number of threads.
+/// Recursively scan the given paths for cargo projects using the specified number of threads.
 ///
 /// When the number of threads is 0, use as many threads as virtual CPU cores.
 pub fn find_cargo_projects(
-    path: &Path,
+    roots: &[PathBuf],
+    excludes: Excludes,
     mut num_threads: usize,
     notify_tx: NotifySender,
 ) -> (
@@ -36,7 +98,7 @@ pub fn find_cargo_projects(
     let progress = Arc::new(NotifyRwLock::new(
         notify_tx,
         Progress {
-            total: 1, // 最初に入っているディレクトリは必ずスキャンする
+            total: roots.len(), // 最初に入っているディレクトリは必ずスキャンする
             scanned: 0,
         },
     ));
@@ -45,7 +107,8 @@ pub fn find_cargo_projects(
     }
 
     let (result_tx, result_rx) = unbounded();
-    let path = path.to_owned();
+    let roots = roots.to_vec();
+    let excludes = Arc::new(excludes);
     std::thread::spawn({
         let progress = progress.clone();
         move || {
@@ -57,19 +120,25 @@ pub fn find_cargo_projects(
                     .for_each(|(job_rx, result_tx)| {
                         scope.spawn({
                             let progress = progress.clone();
+                            let excludes = excludes.clone();
                             || {
                                 job_rx.into_iter().for_each(move |job| {
                                     find_cargo_projects_task(
                                         job,
                                         result_tx.clone(),
                                         progress.clone(),
+                                        &excludes,
                                     )
                                 })
                             }
                         });
                     });
 
-                job_tx.clone().send(Job(path, job_tx)).unwrap();
+                for root in roots {
+                    job_tx.send(Job(root, job_tx.clone())).unwrap();
+                }
+                // The workers stop once every sender, including the ones inside queued jobs, is gone
+                drop(job_tx);
             });
         }
     });
@@ -85,6 +154,7 @@ fn find_cargo_projects_task(
     job: Job,
     results: Sender<anyhow::Result<ProjectTargetAnalysis>>,
     progress: Arc<NotifyRwLock<Progress>>,
+    excludes: &Excludes,
 ) {
     let path = job.0;
     let job_sender = job.1;
@@ -106,6 +176,10 @@ fn find_cargo_projects_task(
     let has_cargo_toml = files
         .iter()
         .any(|it| it.file_name().unwrap_or_default().to_string_lossy() == "Cargo.toml");
+    // There are no projects inside of build output, only more target directories like
+    // rust-analyzer's, so target directories are not searched
+    let is_target_dir = !has_cargo_toml && is_cargo_target_dir(&path);
+    let dirs = if is_target_dir { vec![] } else { dirs };
 
     // Iterate through the subdirectories of path, ignoring entries that caused errors
     for it in dirs {
@@ -115,6 +189,7 @@ fn find_cargo_projects_task(
             // as there shouldn't be any target dirs in there. Even if there are valid target dirs,
             // they should probably not be deleted. See issue #2 (https://github.com/dnlmlr/cargo-clean-all/issues/2)
             ".git" | ".cargo" => (),
+            _ if excludes.is_excluded(&it) => (),
             // For directories queue a new job to search it with the threadpool
             _ => {
                 job_sender
@@ -128,60 +203,405 @@ fn find_cargo_projects_task(
     // If path contains a Cargo.toml, it is a project directory
     if has_cargo_toml {
         results.send(ProjectTargetAnalysis::analyze(&path)).unwrap();
+    } else if is_target_dir && !path.with_file_name("Cargo.toml").is_file() {
+        // The manifest of the project was moved or deleted
+        results
+            .send(Ok(ProjectTargetAnalysis::analyze_orphaned(&path)))
+            .unwrap();
     }
     progress.write().scanned += 1;
 }
 
+/// Drop the orphaned targets among `projects` that turn out to be the target or build directory
+/// of a project, like a `build.target-dir` shared by several projects
+pub fn drop_claimed_orphans(projects: &mut Vec<ProjectTargetAnalysis>) {
+    let claimed = projects
+        .iter()
+        .filter(|it| !it.orphaned)
+        .flat_map(|it| it.artifact_dirs())
+        .collect::<HashSet<_>>();
+    projects.retain(|it| !it.orphaned || !claimed.contains(&it.target_path));
+}
+
+/// Whether `path` is a target directory, tagged by cargo with `CACHEDIR.TAG` or holding the
+/// lock file cargo keeps in its profile directories
+pub fn is_cargo_target_dir(path: &Path) -> bool {
+    let tagged_by_cargo = std::fs::read_to_string(path.join("CACHEDIR.TAG")).is_ok_and(|it| {
+        it.starts_with("Signature: 8a477f597d28d172789f06886806bc55") && it.contains("cargo")
+    });
+    tagged_by_cargo || path.join("debug").join(".cargo-lock").is_file()
+}
+
 #[derive(Clone, Debug)]
 pub struct ProjectTargetAnalysis {
     pub id: Uuid,
-    /// The path of the project without the `target` directory suffix
+    /// The path of the project without the `target` directory suffix, or the target directory
+    /// itself when it is orphaned
     pub project_path: PathBuf,
     /// Cargo project name
     pub project_name: Option<String>,
-    /// The size in bytes that the target directory takes up
+    /// The target directory of the project, `<project_path>/target` unless configured otherwise
+    pub target_path: PathBuf,
+    /// Where cargo keeps intermediate artifacts when `build.build-dir` moves them out of the
+    /// target directory
+    pub build_dir: Option<PathBuf>,
+    /// The size in bytes that the target and build directories take up
     pub size: u64,
-    /// The timestamp of the last recently modified file in the target directory
+    /// The bytes the files of the target and build directories occupy on disk
+    pub allocated_size: u64,
+    /// The timestamp of the last recently modified file in the target and build directories
     pub last_modified: SystemTime,
     /// Indicate that this target directory should be cleaned
     pub selected_for_cleanup: bool,
+    /// Compiler that last built into the target directory, e.g. `1.84.0 (9fc6b4312 2025-01-07)`
+    pub built_with: Option<String>,
+    /// Whether that compiler is still installed, `None` when it is not known
+    pub compiler_installed: Option<bool>,
+    /// Sizes of the build units some clean scopes remove, `None` until
+    /// [`ProjectTargetAnalysis::measure_units`] read the fingerprints
+    pub unit_sizes: Option<UnitSizes>,
+    /// A target directory without a `Cargo.toml` next to it, only cleaned natively
+    pub orphaned: bool,
+    /// Branch checked out in the git repository of the project
+    pub git_branch: Option<String>,
+    /// Toolchain pinned by the project's `rust-toolchain` file, e.g. `nightly-2024-05-01`
+    pub toolchain: Option<String>,
+    /// Number of packages when the project is the root of a workspace
+    pub workspace_members: Option<usize>,
 }
 
 impl ProjectTargetAnalysis {
     /// Analyze a given project directories target directory
     pub fn analyze(path: &Path) -> anyhow::Result<Self> {
-        let (size, last_modified) = Self::recursive_scan_target(path.join("target"));
         let cargo_manifest = Manifest::from_path(path.join("Cargo.toml"))?;
-        Ok(Self {
+        let project_name = cargo_manifest.package.map(|p| p.name);
+        Ok(Self::analyze_target(
+            path,
+            resolve_target_dir(path),
+            resolve_build_dir(path),
+            project_name,
+        ))
+    }
+
+    /// Read the details only some columns show, so the scan does not pay for hidden ones
+    pub fn fill_columns(&mut self, columns: &[Column]) {
+        if self.orphaned {
+            return;
+        }
+        let path = &self.project_path;
+        for column in columns {
+            match column {
+                Column::GitBranch => self.git_branch = vcs::git_branch(path),
+                Column::Toolchain => {
+                    self.toolchain = toolchain::pinned_toolchain(path).map(|it| it.to_string())
+                }
+                Column::WorkspaceMembers => {
+                    self.workspace_members = Manifest::from_path(path.join("Cargo.toml"))
+                        .ok()
+                        .and_then(|manifest| {
+                            let workspace = manifest.workspace.as_ref()?;
+                            Some(count_workspace_members(
+                                path,
+                                workspace,
+                                manifest.package.is_some(),
+                            ))
+                        })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Analyze a target directory whose project is gone
+    pub fn analyze_orphaned(target_path: &Path) -> Self {
+        Self {
+            orphaned: true,
+            ..Self::analyze_target(target_path, target_path.to_owned(), None, None)
+        }
+    }
+
+    fn analyze_target(
+        project_path: &Path,
+        target_path: PathBuf,
+        build_dir: Option<PathBuf>,
+        project_name: Option<String>,
+    ) -> Self {
+        let dirs = [Some(&target_path), build_dir.as_ref()];
+        let dirs = dirs.into_iter().flatten().collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let (size, allocated_size, last_modified) = dirs
+            .iter()
+            .map(|it| Self::scan_target(it, &mut seen))
+            .fold((0, 0, SystemTime::UNIX_EPOCH), |a, b| {
+                (a.0 + b.0, a.1 + b.1, a.2.max(b.2))
+            });
+        let built_with = dirs
+            .iter()
+            .find_map(|it| fingerprint::read_rustc_version(it));
+        let compiler_installed = built_with
+            .as_ref()
+            .map(|it| fingerprint::installed_compilers().contains(it));
+        Self {
             id: Uuid::new_v4(),
-            project_path: path.to_owned(),
-            project_name: cargo_manifest.package.map(|p| p.name),
+            project_path: project_path.to_owned(),
+            project_name,
+            target_path,
+            build_dir,
             size,
+            allocated_size,
             last_modified,
             selected_for_cleanup: false,
-        })
+            built_with,
+            compiler_installed,
+            unit_sizes: None,
+            orphaned: false,
+            git_branch: None,
+            toolchain: None,
+            workspace_members: None,
+        }
+    }
+
+    /// Measure the build units of the target and build directories once, which takes reading
+    /// every fingerprint
+    pub fn measure_units(&mut self) -> UnitSizes {
+        let dirs = self.artifact_dirs();
+        *self
+            .unit_sizes
+            .get_or_insert_with(|| UnitSizes::measure(&dirs))
+    }
+
+    /// The target directory followed by the build directory, if the project has one
+    pub fn artifact_dirs(&self) -> Vec<PathBuf> {
+        std::iter::once(self.target_path.clone())
+            .chain(self.build_dir.clone())
+            .collect()
     }
 
     // Recursively sum up the file sizes and find the last modified timestamp
-    fn recursive_scan_target<T: AsRef<Path>>(path: T) -> (u64, SystemTime) {
-        let path = path.as_ref();
+    pub(crate) fn recursive_scan_target<T: AsRef<Path>>(path: T) -> (u64, SystemTime) {
+        let (size, _, last_modified) = Self::scan_target(path.as_ref(), &mut HashSet::new());
+        (size, last_modified)
+    }
 
-        let default = (0, SystemTime::UNIX_EPOCH);
+    // Recursively sum up the file sizes and the space they occupy on disk, and find the last
+    // modified timestamp. Blocks of hard linked files are only counted for the first link found,
+    // links already in `seen` add their length but no space.
+    fn scan_target(path: &Path, seen: &mut HashSet<(u64, u64)>) -> (u64, u64, SystemTime) {
+        let default = (0, 0, SystemTime::UNIX_EPOCH);
 
         if !path.exists() {
             return default;
         }
 
         match (path.is_file(), path.metadata()) {
-            (true, Ok(md)) => (md.len(), md.modified().unwrap_or(default.1)),
+            (true, Ok(md)) => {
+                let first_link = disk::hard_link_id(&md).is_none_or(|it| seen.insert(it));
+                (
+                    md.len(),
+                    if first_link {
+                        disk::allocated_size(&md)
+                    } else {
+                        0
+                    },
+                    md.modified().unwrap_or(default.2),
+                )
+            }
             _ => path
                 .read_dir()
                 .map(|rd| {
                     rd.filter_map(|it| it.ok().map(|it| it.path()))
-                        .map(Self::recursive_scan_target)
-                        .fold(default, |a, b| (a.0 + b.0, a.1.max(b.1)))
+                        .map(|it| Self::scan_target(&it, seen))
+                        .fold(default, |a, b| (a.0 + b.0, a.1 + b.1, a.2.max(b.2)))
                 })
                 .unwrap_or(default),
         }
     }
 }
+
+/// Bytes of the build units single clean scopes remove
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnitSizes {
+    /// Build units compiled by another compiler than the current one
+    pub stale_toolchain: u64,
+    /// Build units superseded by another variant of the same crate
+    pub duplicate: u64,
+    /// rust-analyzer's target directory and the metadata-only builds of `cargo check` and clippy
+    pub check: u64,
+}
+
+impl UnitSizes {
+    /// Measure the target directory `dirs[0]` and the build directory following it
+    pub fn measure(dirs: &[PathBuf]) -> Self {
+        let units = dirs
+            .iter()
+            .flat_map(|it| fingerprint::scan_units(it))
+            .collect::<Vec<_>>();
+        let sum = |units: Vec<&fingerprint::BuildUnit>| units.iter().map(|it| it.size).sum();
+        Self {
+            stale_toolchain: sum(fingerprint::stale_toolchain_units(&units)),
+            duplicate: sum(fingerprint::superseded_units(&units)),
+            check: fingerprint::check_artifacts(&dirs[0], &units).size(),
+        }
+    }
+}
+
+/// Packages of the workspace rooted at `path`: the directories with a manifest matched by the
+/// `members` globs and not excluded, plus the root package
+fn count_workspace_members(
+    path: &Path,
+    workspace: &cargo_toml::Workspace,
+    root_package: bool,
+) -> usize {
+    let excluded = workspace
+        .exclude
+        .iter()
+        .map(|it| path.join(it))
+        .collect::<Vec<_>>();
+    let members = workspace
+        .members
+        .iter()
+        .filter_map(|it| glob::glob(&path.join(it).to_string_lossy()).ok())
+        .flatten()
+        .filter_map(|it| it.ok())
+        .filter(|it| it.join("Cargo.toml").is_file() && *it != path)
+        .filter(|it| !excluded.iter().any(|excluded| it.starts_with(excluded)))
+        .collect::<HashSet<_>>();
+    members.len() + usize::from(root_package)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn orphaned_targets_are_found() {
+        let root = TempDir::new("orphans");
+        let tag = "Signature: 8a477f597d28d172789f06886806bc55\n\
+                   # This file is a cache directory tag created by cargo.\n";
+        let project = root.join("project");
+        std::fs::create_dir_all(project.join("target/rust-analyzer")).unwrap();
+        std::fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"project\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        std::fs::write(project.join("target/CACHEDIR.TAG"), tag).unwrap();
+        std::fs::write(project.join("target/rust-analyzer/CACHEDIR.TAG"), tag).unwrap();
+        std::fs::create_dir_all(root.join("moved/target")).unwrap();
+        std::fs::write(root.join("moved/target/CACHEDIR.TAG"), tag).unwrap();
+        std::fs::create_dir_all(root.join("old/build/debug")).unwrap();
+        std::fs::write(root.join("old/build/debug/.cargo-lock"), "").unwrap();
+
+        let (notify_tx, _notify_rx) = std::sync::mpsc::sync_channel(1);
+        let (receiver, _) =
+            find_cargo_projects(&[root.to_path_buf()], Excludes::default(), 2, notify_tx);
+        let mut projects = receiver.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        projects.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+        let found = projects
+            .iter()
+            .map(|it| (it.target_path.strip_prefix(&root).unwrap(), it.orphaned))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Path::new("moved/target"), true),
+                (Path::new("old/build"), true),
+                (Path::new("project/target"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn build_dir_belongs_to_its_project() {
+        let root = TempDir::new("build-dir");
+        let project = root.join("project");
+        std::fs::create_dir_all(project.join(".cargo")).unwrap();
+        std::fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"project\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".cargo/config.toml"),
+            "[build]\nbuild-dir = \"../build\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(project.join("target/debug")).unwrap();
+        std::fs::write(project.join("target/debug/project"), [0; 10]).unwrap();
+        std::fs::create_dir_all(root.join("build/debug/deps")).unwrap();
+        std::fs::write(root.join("build/debug/.cargo-lock"), "").unwrap();
+        std::fs::write(root.join("build/debug/deps/libproject.rlib"), [0; 100]).unwrap();
+
+        let (notify_tx, _notify_rx) = std::sync::mpsc::sync_channel(1);
+        let (receiver, _) =
+            find_cargo_projects(&[root.to_path_buf()], Excludes::default(), 2, notify_tx);
+        let mut projects = receiver.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        drop_claimed_orphans(&mut projects);
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].build_dir, Some(root.join("build")));
+        assert_eq!(projects[0].size, 110);
+        // The build units are only read when a clean scope or `list` needs their sizes
+        assert_eq!(projects[0].unit_sizes, None);
+        assert_eq!(projects[0].measure_units(), UnitSizes::default());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn hard_links_take_space_once() {
+        let root = TempDir::new("links");
+        std::fs::create_dir_all(root.join("debug/deps")).unwrap();
+        std::fs::write(root.join("debug/deps/tool-0123"), [1; 8192]).unwrap();
+        std::fs::hard_link(root.join("debug/deps/tool-0123"), root.join("debug/tool")).unwrap();
+        let metadata = std::fs::metadata(root.join("debug/tool")).unwrap();
+
+        let analysis = ProjectTargetAnalysis::analyze_orphaned(&root);
+        assert_eq!(analysis.size, 2 * 8192);
+        assert_eq!(analysis.allocated_size, disk::allocated_size(&metadata));
+    }
+
+    #[test]
+    fn workspace_build_dir_is_counted_once() {
+        let root = TempDir::new("build-dir");
+        let member = root.join("member");
+        std::fs::create_dir_all(root.join(".cargo")).unwrap();
+        std::fs::create_dir_all(&member).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join(".cargo/config.toml"),
+            "[build]\nbuild-dir = \"{workspace-root}/build\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("build/debug/deps")).unwrap();
+        std::fs::write(root.join("build/debug/.cargo-lock"), "").unwrap();
+        std::fs::write(root.join("build/debug/deps/libmember.rlib"), [0; 100]).unwrap();
+
+        let (notify_tx, _notify_rx) = std::sync::mpsc::sync_channel(1);
+        let (receiver, _) =
+            find_cargo_projects(&[root.to_path_buf()], Excludes::default(), 2, notify_tx);
+        let mut projects = receiver.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        drop_claimed_orphans(&mut projects);
+        projects.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+        let found = projects
+            .iter()
+            .map(|it| (it.project_path.as_path(), it.build_dir.as_deref(), it.size))
+            .collect::<Vec<_>>();
+        let build_dir = root.join("build");
+        assert_eq!(
+            found,
+            [
+                (root.as_path(), Some(build_dir.as_path()), 100),
+                (member.as_path(), None, 0),
+            ]
+        );
+    }
+}
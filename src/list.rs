@@ -0,0 +1,341 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::cargo_home::{CacheEntry, CacheRemoval};
+use crate::columns::Column;
+use crate::config::Config;
+use crate::filter::ProjectFilter;
+use crate::units::{format_timestamp, SizeUnit};
+use crate::{
+    drop_claimed_orphans, find_cargo_projects, Excludes, ProjectTargetAnalysis, ORPHANED_TARGET,
+};
+
+/// Output format of the non-interactive commands
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned plain-text table
+    #[default]
+    Table,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+}
+
+/// Run the project finder to completion.
+///
+/// Returns the analyses matching `filter` ordered by the configured sort order, together with the
+/// errors of projects that could not be analyzed.
+pub fn collect_projects(
+    config: &Config,
+    filter: &ProjectFilter,
+) -> (Vec<ProjectTargetAnalysis>, Vec<anyhow::Error>) {
+    // Nobody redraws anything, the notifications are only kept from failing
+    let (notify_tx, _notify_rx) = std::sync::mpsc::sync_channel(1);
+    let (analysis_receiver, _progress) = find_cargo_projects(
+        &config.search_roots,
+        Excludes::new(&config.excludes),
+        config.scan_workers,
+        notify_tx,
+    );
+
+    let now = SystemTime::now();
+    let (mut projects, errors): (Vec<_>, Vec<_>) = analysis_receiver.into_iter().partition_result();
+    drop_claimed_orphans(&mut projects);
+    // Details of optional columns are only needed to sort by them
+    let sort_columns = Column::ALL
+        .into_iter()
+        .filter(|it| it.sort_key() == config.sort.key)
+        .collect::<Vec<_>>();
+    let projects = projects
+        .into_iter()
+        .filter(|it| filter.matches(it, now))
+        .update(|it| it.fill_columns(&sort_columns))
+        .sorted_by(|a, b| config.sort.compare(a, b))
+        .collect();
+    (projects, errors)
+}
+
+#[derive(Serialize)]
+struct ProjectRecord<'a> {
+    path: &'a Path,
+    name: Option<&'a str>,
+    size: u64,
+    last_modified: String,
+    target_path: &'a Path,
+    build_dir: Option<&'a Path>,
+    built_with: Option<&'a str>,
+    compiler_installed: Option<bool>,
+    stale_toolchain_size: u64,
+    duplicate_size: u64,
+    check_size: u64,
+    orphaned: bool,
+}
+
+impl<'a> From<&'a ProjectTargetAnalysis> for ProjectRecord<'a> {
+    fn from(project: &'a ProjectTargetAnalysis) -> Self {
+        let unit_sizes = project.unit_sizes.unwrap_or_default();
+        Self {
+            path: &project.project_path,
+            name: project.project_name.as_deref(),
+            size: project.size,
+            last_modified: format_timestamp(project.last_modified),
+            target_path: &project.target_path,
+            build_dir: project.build_dir.as_deref(),
+            built_with: project.built_with.as_deref(),
+            compiler_installed: project.compiler_installed,
+            stale_toolchain_size: unit_sizes.stale_toolchain,
+            duplicate_size: unit_sizes.duplicate,
+            check_size: unit_sizes.check,
+            orphaned: project.orphaned,
+        }
+    }
+}
+
+/// Write `projects` to `out`. `size_unit` is only used by the table, the machine readable
+/// formats always report sizes in bytes.
+pub fn write_projects(
+    out: &mut impl Write,
+    projects: &[ProjectTargetAnalysis],
+    format: OutputFormat,
+    size_unit: SizeUnit,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Table => write_table(out, projects, size_unit)?,
+        OutputFormat::Json => {
+            let records = projects.iter().map(ProjectRecord::from).collect_vec();
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => {
+            for project in projects {
+                serde_json::to_writer(&mut *out, &ProjectRecord::from(project))?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "path,name,size,last_modified,target_path,build_dir,built_with,compiler_installed,stale_toolchain_size,duplicate_size,check_size,orphaned"
+            )?;
+            for project in projects {
+                let record = ProjectRecord::from(project);
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{},{},{}",
+                    csv_field(&record.path.to_string_lossy()),
+                    csv_field(record.name.unwrap_or_default()),
+                    record.size,
+                    record.last_modified,
+                    csv_field(&record.target_path.to_string_lossy()),
+                    record
+                        .build_dir
+                        .map(|it| csv_field(&it.to_string_lossy()))
+                        .unwrap_or_default(),
+                    csv_field(record.built_with.unwrap_or_default()),
+                    record
+                        .compiler_installed
+                        .map(|it| it.to_string())
+                        .unwrap_or_default(),
+                    record.stale_toolchain_size,
+                    record.duplicate_size,
+                    record.check_size,
+                    record.orphaned,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_table(
+    out: &mut impl Write,
+    projects: &[ProjectTargetAnalysis],
+    size_unit: SizeUnit,
+) -> anyhow::Result<()> {
+    let header = [
+        "PATH",
+        "NAME",
+        "SIZE",
+        "DUPLICATES",
+        "LAST MODIFIED",
+        "BUILT WITH",
+        "TARGET",
+    ];
+    let rows = projects
+        .iter()
+        .map(|it| {
+            [
+                it.project_path.to_string_lossy().into_owned(),
+                match it.orphaned {
+                    true => ORPHANED_TARGET.to_string(),
+                    false => it.project_name.clone().unwrap_or_default(),
+                },
+                size_unit.format(it.size),
+                size_unit.format(it.unit_sizes.unwrap_or_default().duplicate),
+                format_timestamp(it.last_modified),
+                built_with_text(it),
+                it.target_path.to_string_lossy().into_owned(),
+            ]
+        })
+        .collect_vec();
+    write_aligned(out, header, &rows, &[2, 3])
+}
+
+/// Write cache `entries` as a table, sized by the parts `removal` removes
+pub fn write_cache_entries(
+    out: &mut impl Write,
+    entries: &[CacheEntry],
+    removal: CacheRemoval,
+    size_unit: SizeUnit,
+) -> anyhow::Result<()> {
+    let header = ["GROUP", "NAME", "VERSION", "SIZE", "LAST USED", "IN USE"];
+    let rows = entries
+        .iter()
+        .map(|it| {
+            [
+                it.group.clone(),
+                it.name.clone(),
+                it.version.clone(),
+                size_unit.format(it.size(removal)),
+                format_timestamp(it.last_used),
+                match it.in_use {
+                    Some(true) => "yes".to_string(),
+                    Some(false) => "no".to_string(),
+                    None => String::new(),
+                },
+            ]
+        })
+        .collect_vec();
+    write_aligned(out, header, &rows, &[3])
+}
+
+/// Write `rows` below `header` in columns, right-aligning the `size_columns`
+fn write_aligned<const N: usize>(
+    out: &mut impl Write,
+    header: [&str; N],
+    rows: &[[String; N]],
+    size_columns: &[usize],
+) -> anyhow::Result<()> {
+    let header = header.map(str::to_string);
+    let mut widths = header.clone().map(|it| it.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // Sizes read best aligned at the unit
+                if size_columns.contains(&column) {
+                    format!("{cell:>width$}")
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Release of the compiler the target was built with, marked when it is no longer installed
+fn built_with_text(project: &ProjectTargetAnalysis) -> String {
+    let release = project
+        .built_with
+        .as_deref()
+        .and_then(|it| it.split(' ').next())
+        .unwrap_or_default();
+    match project.compiler_installed {
+        Some(false) => format!("{release} (removed)"),
+        _ => release.to_string(),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn project(path: &str, name: Option<&str>, size: u64) -> ProjectTargetAnalysis {
+        ProjectTargetAnalysis {
+            id: Uuid::new_v4(),
+            project_path: PathBuf::from(path),
+            project_name: name.map(str::to_string),
+            target_path: PathBuf::from(path).join("target"),
+            build_dir: None,
+            size,
+            allocated_size: 0,
+            last_modified: SystemTime::UNIX_EPOCH,
+            selected_for_cleanup: false,
+            built_with: None,
+            compiler_installed: None,
+            unit_sizes: None,
+            orphaned: false,
+            git_branch: None,
+            toolchain: None,
+            workspace_members: None,
+        }
+    }
+
+    fn render(projects: &[ProjectTargetAnalysis], format: OutputFormat) -> String {
+        let mut out = vec![];
+        write_projects(&mut out, projects, format, SizeUnit::Mib).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_special_characters() {
+        let projects = [project("/work/a,b", Some("a"), 42)];
+        assert_eq!(
+            render(&projects, OutputFormat::Csv),
+            "path,name,size,last_modified,target_path,build_dir,built_with,compiler_installed,stale_toolchain_size,duplicate_size,check_size,orphaned\n\
+             \"/work/a,b\",a,42,1970-01-01T00:00:00Z,\"/work/a,b/target\",,,,0,0,0,false\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_writes_one_object_per_line() {
+        let projects = [project("/a", Some("a"), 1), project("/b", None, 2)];
+        let output = render(&projects, OutputFormat::Ndjson);
+        let lines = output.lines().collect_vec();
+        assert_eq!(lines.len(), 2);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["path"], "/b");
+        assert_eq!(second["name"], serde_json::Value::Null);
+        assert_eq!(second["size"], 2);
+    }
+
+    #[test]
+    fn table_columns_are_aligned() {
+        let projects = [
+            project("/short", Some("s"), 1024 * 1024),
+            project("/much/longer/path", Some("heavy"), 10 * 1024 * 1024),
+        ];
+        let output = render(&projects, OutputFormat::Table);
+        let lines = output.lines().collect_vec();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].find("NAME"), lines[2].find("heavy"));
+        assert!(lines[1].contains(" 1.00 MiB"));
+        assert!(lines[2].contains("10.00 MiB"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// A `[[package]]` of a `Cargo.lock`
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// e.g. `registry+https://github.com/rust-lang/crates.io-index`, `None` for path dependencies
+    pub source: Option<String>,
+}
+
+impl LockedPackage {
+    /// Whether the package is downloaded from a registry (as opposed to git or a path)
+    pub fn is_from_registry(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|it| it.starts_with("registry+") || it.starts_with("sparse+"))
+    }
+}
+
+#[derive(Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// The `Cargo.lock` used by the project at `project_path`, in the project or the root of its
+/// workspace
+pub fn find_lockfile(project_path: &Path) -> Option<PathBuf> {
+    project_path
+        .ancestors()
+        .map(|it| it.join("Cargo.lock"))
+        .find(|it| it.is_file())
+}
+
+pub fn read_lockfile(path: &Path) -> anyhow::Result<Vec<LockedPackage>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let lockfile: Lockfile =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(lockfile.package)
+}
+
+/// Every package locked by any of `project_paths`. Projects of the same workspace share their
+/// lockfile, which is read only once.
+pub fn collect_locked_packages<'a>(
+    project_paths: impl IntoIterator<Item = &'a Path>,
+) -> (HashSet<LockedPackage>, Vec<anyhow::Error>) {
+    let lockfiles = project_paths
+        .into_iter()
+        .filter_map(find_lockfile)
+        .collect::<HashSet<_>>();
+    let mut packages = HashSet::new();
+    let mut errors = vec![];
+    for lockfile in lockfiles {
+        match read_lockfile(&lockfile) {
+            Ok(locked) => packages.extend(locked),
+            Err(err) => errors.push(err),
+        }
+    }
+    (packages, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn workspace_members_share_the_root_lockfile() {
+        let root = TempDir::new("lock");
+        let member = root.join("crates").join("member");
+        std::fs::create_dir_all(&member).unwrap();
+        std::fs::write(
+            root.join("Cargo.lock"),
+            r#"
+version = 4
+
+[[package]]
+name = "member"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.217"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "02fc4265df13d6fa1d00ecff087228cc0a2b5f3c0e87e258d8b94a156e984c70"
+"#,
+        )
+        .unwrap();
+
+        let (packages, errors) = collect_locked_packages([root.as_path(), member.as_path()]);
+        assert!(errors.is_empty());
+        assert_eq!(packages.len(), 2);
+        let serde = packages.iter().find(|it| it.name == "serde").unwrap();
+        assert!(serde.is_from_registry());
+        assert!(!packages
+            .iter()
+            .any(|it| it.name == "member" && it.is_from_registry()));
+    }
+}
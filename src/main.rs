@@ -1,16 +1,33 @@
-use clap::Parser;
-use std::path::PathBuf;
-use std::{error::Error, io};
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
 
-use cargo_cleaner::find_cargo_projects;
+use cargo_cleaner::cargo_config::{resolve_build_dir, resolve_target_dir};
+use cargo_cleaner::cargo_home::{CacheKind, CacheRemoval, ProjectReferences};
+use cargo_cleaner::clean::{clean_and_measure, reclaimable_size, CleanScope, DeletionBackend};
+use cargo_cleaner::columns::Column;
+use cargo_cleaner::config::{Config, ConfigLayer, DaemonLayer};
+use cargo_cleaner::disk::DiskSpace;
+use cargo_cleaner::filter::ProjectFilter;
+use cargo_cleaner::fingerprint;
+use cargo_cleaner::goal::{plan_free_space, plan_min_free};
+use cargo_cleaner::journal::{self, JournalEntry};
+use cargo_cleaner::list::{collect_projects, write_cache_entries, write_projects, OutputFormat};
+use cargo_cleaner::schedule::{self, Every, Schedule, SERVICE_NAME, TIMER_NAME};
+use cargo_cleaner::sort::SortOrder;
 use cargo_cleaner::tui::{Event, Tui};
 use cargo_cleaner::tui_app::{ui, App};
+use cargo_cleaner::units::{parse_duration, parse_size, ByteSize, HumanDuration, SizeUnit};
+use cargo_cleaner::{find_cargo_projects, Excludes, ProjectTargetAnalysis};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture, Event as CrosstermEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use dirs::home_dir;
 use ratatui::prelude::CrosstermBackend;
 use ratatui::Terminal;
 use std::sync::Arc;
@@ -25,29 +42,885 @@ enum CargoCli {
 #[derive(clap::Args)]
 #[command(author, version, about)]
 struct Args {
-    #[arg(long, default_value = "false")]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Preselect the stalest projects that free at least this much, e.g. 50GiB
+    #[arg(long, value_parser = parse_size)]
+    free: Option<u64>,
+    /// Preselect the stalest projects needed to leave this much free space on every
+    /// filesystem holding projects, e.g. 100GiB
+    #[arg(long, value_parser = parse_size, conflicts_with = "free")]
+    min_free: Option<u64>,
+    /// Only show projects whose target directory is at least this large, e.g. 500MiB.
+    /// Projects without build output are hidden unless this is given, `a` shows all.
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<u64>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Scan for projects and print them without starting the TUI.
+    ///
+    /// Exits with 0 when at least one project was listed, 1 when nothing matched and 2 on errors.
+    List {
+        #[arg(short = 'f', long, value_enum, default_value_t)]
+        format: OutputFormat,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Clean the matching projects without starting the TUI.
+    ///
+    /// Asks for confirmation on a terminal unless --yes is given, refuses to run unattended
+    /// without it. Exits with 1 when the cleanup was aborted or a project failed to clean.
+    Clean {
+        /// Do not ask for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Watch free disk space and clean the stalest unprotected targets when it runs low.
+    ///
+    /// Every cleanup is recorded in the journal. Stops on SIGTERM or Ctrl-C.
+    Daemon {
+        /// Do a single check and exit
+        #[arg(long)]
+        once: bool,
+        #[command(flatten)]
+        policy: DaemonArgs,
+    },
+    /// Analyze a cache in cargo's home and optionally remove entries no project needs.
+    ///
+    /// `binaries` lists the packages installed by `cargo install`, --clean uninstalls them.
+    /// `toolchains` lists the toolchains installed by rustup, leaving out the default toolchain and
+    /// the one rustup runs in the current directory.
+    /// Without --clean the entries are listed together with the reclaimable size. Exits with 1
+    /// when nothing matched, the cleanup was aborted or an entry failed to be removed.
+    Cache {
+        #[arg(value_enum)]
+        kind: CacheKind,
+        /// Only entries no Cargo.lock, rust-toolchain file or rust-version of a scanned project
+        /// references. Installed binaries are unused when they were not run for --older-than
+        #[arg(long)]
+        unused: bool,
+        /// With --unused, only entries not used for this long. Defaults to the
+        /// `cache-older-than` setting
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+        /// Which parts of registry entries are removed
+        #[arg(long, value_enum, default_value_t)]
+        remove: CacheRemoval,
+        /// Remove the listed entries
+        #[arg(long)]
+        clean: bool,
+        /// Do not ask for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Record the current time in the target directory of a project, for `sweep --since-stamp`
+    Stamp {
+        /// Project directory
+        #[arg(default_value = ".")]
+        project: PathBuf,
+    },
+    /// Remove the build artifacts of a project not used since it was stamped.
+    ///
+    /// Meant for CI caches: run `stamp` before the build and `sweep --since-stamp` after it to
+    /// keep only what the build used. Build units are removed as a whole.
+    Sweep {
+        /// Project directory that was stamped with `cargo cleaner stamp`
+        #[arg(long, value_name = "PROJECT")]
+        since_stamp: PathBuf,
+    },
+    /// Run `clean` periodically from a systemd user timer
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommand {
+    /// Write and enable a systemd user service and timer running `clean --yes` with the given
+    /// filters and options
+    Install {
+        #[arg(long, value_enum, default_value_t)]
+        every: Every,
+        /// Only print the generated units instead of installing them
+        #[arg(long)]
+        print: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Show the installed schedule and when it runs next
+    Status,
+    /// Disable the timer and delete the generated units
+    Remove,
+}
+
+/// Overrides for the `[daemon]` table of the config file
+#[derive(clap::Args)]
+struct DaemonArgs {
+    /// Start cleaning when a filesystem has less than this available, e.g. 10GiB
+    #[arg(long, value_parser = parse_size)]
+    min_free: Option<u64>,
+    /// Clean until this much is available again, e.g. 20GiB
+    #[arg(long, value_parser = parse_size)]
+    target_free: Option<u64>,
+    /// Time between full rescans, e.g. 1h
+    #[arg(long, value_parser = parse_duration)]
+    interval: Option<Duration>,
+    /// Time between free-space checks, e.g. 1m
+    #[arg(long, value_parser = parse_duration)]
+    poll_interval: Option<Duration>,
+    /// Never clean targets used more recently than this, e.g. 7d
+    #[arg(long, value_parser = parse_duration)]
+    older_than: Option<Duration>,
+}
+
+impl From<&DaemonArgs> for DaemonLayer {
+    fn from(args: &DaemonArgs) -> Self {
+        Self {
+            min_free: args.min_free.map(ByteSize),
+            target_free: args.target_free.map(ByteSize),
+            interval: args.interval.map(HumanDuration),
+            poll_interval: args.poll_interval.map(HumanDuration),
+            older_than: args.older_than.map(HumanDuration),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration after merging config files and flags
+    Show,
+}
+
+/// Options that can also be set in the config file. Flags take precedence over config files.
+#[derive(clap::Args)]
+struct ConfigArgs {
+    /// Use this config file instead of ~/.config/cargo-cleaner/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[arg(long, global = true)]
     dry_run: bool,
-    #[arg(short = 'r', long)]
-    search_root: Option<String>,
-    #[arg(short = 'p', long)]
+    /// Directory to search for cargo projects, can be given multiple times
+    #[arg(short = 'r', long, global = true)]
+    search_root: Vec<PathBuf>,
+    /// Directory name or path to skip while searching, can be given multiple times
+    #[arg(short = 'e', long, global = true)]
+    exclude: Vec<String>,
+    #[arg(short = 'p', long, global = true)]
     scan_workers: Option<usize>,
+    #[arg(long, global = true, value_enum)]
+    size_unit: Option<SizeUnit>,
+    /// Sort order as `<key>[:asc|:desc]`, the key being one of the columns, e.g. size:desc
+    #[arg(long, global = true)]
+    sort: Option<SortOrder>,
+    /// Columns of the project list in the order they are shown, e.g. path,size,git-branch
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    columns: Vec<Column>,
+    #[arg(long, global = true, value_enum)]
+    clean_scope: Option<CleanScope>,
+    #[arg(long, global = true, value_enum)]
+    deletion_backend: Option<DeletionBackend>,
+    /// With the sweep clean scope, remove build artifacts not used for this long, e.g. 14d
+    #[arg(long, global = true, value_parser = parse_duration)]
+    sweep_older_than: Option<Duration>,
+}
+
+/// Criteria narrowing down the projects a non-interactive command works on
+#[derive(clap::Args)]
+struct FilterArgs {
+    /// Only projects whose target directory is at least this large, e.g. 500MiB.
+    /// Projects without build output are skipped unless this is given.
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<u64>,
+    /// Only projects whose target directory was not modified for this long, e.g. 30d
+    #[arg(long, value_parser = parse_duration)]
+    older_than: Option<Duration>,
+    /// Only projects whose path matches this glob, e.g. '/home/*/work/**'
+    #[arg(long)]
+    path_glob: Option<glob::Pattern>,
+    /// Only projects whose name matches this regex
+    #[arg(long)]
+    name_regex: Option<regex::Regex>,
+    /// Only projects last built by a compiler that is no longer installed
+    #[arg(long)]
+    compiler_missing: bool,
+    /// Of the matching projects, only the stalest ones needed to free this much, e.g. 50GiB
+    #[arg(long, value_parser = parse_size)]
+    free: Option<u64>,
+    /// Of the matching projects, only the stalest ones needed to leave this much free space on
+    /// every filesystem holding projects, e.g. 100GiB
+    #[arg(long, value_parser = parse_size, conflicts_with = "free")]
+    min_free: Option<u64>,
+}
+
+impl FilterArgs {
+    fn collect(self, config: &Config) -> (Vec<ProjectTargetAnalysis>, Vec<anyhow::Error>) {
+        let (free, min_free) = (self.free, self.min_free);
+        let (projects, errors) = collect_projects(config, &self.into());
+        // The clean scope may free only part of each target
+        let options = config.clean_options();
+        let size = |it: &ProjectTargetAnalysis| reclaimable_size(it, &options);
+        let plan = match (free, min_free) {
+            (Some(goal), _) => plan_free_space(&projects, goal, SystemTime::now(), size),
+            (_, Some(min_free)) => plan_min_free(
+                &projects,
+                min_free,
+                &mut DiskSpace::default(),
+                SystemTime::now(),
+                size,
+            ),
+            (None, None) => return (projects, errors),
+        };
+
+        if !plan.reached() {
+            eprintln!(
+                "warning: only {} can be freed, less than the goal of {}",
+                config.size_unit.format(plan.reclaimed),
+                config.size_unit.format(plan.goal)
+            );
+        }
+        let projects = projects
+            .into_iter()
+            .filter(|it| plan.selected.contains(&it.id))
+            .collect();
+        (projects, errors)
+    }
+}
+
+impl FilterArgs {
+    /// The command line flags reproducing these filters
+    fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(min_size) = self.min_size {
+            args.extend(["--min-size".to_string(), ByteSize(min_size).to_string()]);
+        }
+        if let Some(older_than) = self.older_than {
+            args.extend([
+                "--older-than".to_string(),
+                HumanDuration(older_than).to_string(),
+            ]);
+        }
+        if let Some(path_glob) = &self.path_glob {
+            args.extend(["--path-glob".to_string(), path_glob.as_str().to_string()]);
+        }
+        if let Some(name_regex) = &self.name_regex {
+            args.extend(["--name-regex".to_string(), name_regex.as_str().to_string()]);
+        }
+        if self.compiler_missing {
+            args.push("--compiler-missing".to_string());
+        }
+        if let Some(free) = self.free {
+            args.extend(["--free".to_string(), ByteSize(free).to_string()]);
+        }
+        if let Some(min_free) = self.min_free {
+            args.extend(["--min-free".to_string(), ByteSize(min_free).to_string()]);
+        }
+        args
+    }
+}
+
+impl From<FilterArgs> for ProjectFilter {
+    fn from(args: FilterArgs) -> Self {
+        Self {
+            min_size: args.min_size,
+            older_than: args.older_than,
+            path_glob: args.path_glob,
+            name_regex: args.name_regex,
+            compiler_missing: args.compiler_missing,
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+impl ConfigArgs {
+    fn load(&self, daemon: Option<DaemonLayer>) -> anyhow::Result<Config> {
+        let cli = ConfigLayer {
+            search_roots: Some(self.search_root.clone()).filter(|it| !it.is_empty()),
+            excludes: Some(self.exclude.clone()).filter(|it| !it.is_empty()),
+            scan_workers: self.scan_workers,
+            size_unit: self.size_unit,
+            sort: self.sort,
+            columns: Some(self.columns.clone()).filter(|it| !it.is_empty()),
+            clean_scope: self.clean_scope,
+            deletion_backend: self.deletion_backend,
+            dry_run: self.dry_run.then_some(true),
+            protected: None,
+            cache_older_than: None,
+            sweep_older_than: self.sweep_older_than.map(HumanDuration),
+            daemon,
+        };
+        Config::load(self.config.as_deref(), &std::env::current_dir()?, cli)
+    }
+
+    /// The command line flags reproducing the options given explicitly, with paths made absolute
+    fn to_args(&self) -> Vec<String> {
+        let absolute = |path: &PathBuf| {
+            std::path::absolute(path)
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string()
+        };
+        let mut args = vec![];
+        if let Some(config) = &self.config {
+            args.extend(["--config".to_string(), absolute(config)]);
+        }
+        if self.dry_run {
+            args.push("--dry-run".to_string());
+        }
+        for root in &self.search_root {
+            args.extend(["-r".to_string(), absolute(root)]);
+        }
+        for exclude in &self.exclude {
+            args.extend(["-e".to_string(), exclude.clone()]);
+        }
+        if let Some(scan_workers) = self.scan_workers {
+            args.extend(["-p".to_string(), scan_workers.to_string()]);
+        }
+        if let Some(size_unit) = self.size_unit {
+            args.extend(["--size-unit".to_string(), size_unit.label().to_lowercase()]);
+        }
+        if let Some(sort) = self.sort {
+            args.extend(["--sort".to_string(), sort.to_string()]);
+        }
+        if !self.columns.is_empty() {
+            let columns = self
+                .columns
+                .iter()
+                .map(|it| it.as_str())
+                .collect::<Vec<_>>();
+            args.extend(["--columns".to_string(), columns.join(",")]);
+        }
+        if let Some(clean_scope) = self.clean_scope {
+            args.extend([
+                "--clean-scope".to_string(),
+                clean_scope.as_str().to_string(),
+            ]);
+        }
+        if let Some(deletion_backend) = self.deletion_backend {
+            args.extend([
+                "--deletion-backend".to_string(),
+                deletion_backend.as_str().to_string(),
+            ]);
+        }
+        if let Some(sweep_older_than) = self.sweep_older_than {
+            args.extend([
+                "--sweep-older-than".to_string(),
+                HumanDuration(sweep_older_than).to_string(),
+            ]);
+        }
+        args
+    }
+}
+
+fn main() -> ExitCode {
     let CargoCli::Cleaner(args) = CargoCli::parse();
 
+    match run(args) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: Args) -> anyhow::Result<ExitCode> {
+    let daemon = match &args.command {
+        Some(Command::Daemon { policy, .. }) => Some(policy.into()),
+        _ => None,
+    };
+    let config = args.config.load(daemon)?;
+
+    match args.command {
+        Some(Command::Config {
+            action: ConfigCommand::Show,
+        }) => {
+            print!("{}", config.to_toml()?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::List { format, filter }) => run_list(&config, format, filter),
+        Some(Command::Clean { yes, filter }) => run_clean(&config, yes, filter),
+        Some(Command::Daemon { once, .. }) => {
+            let stop = Arc::new(AtomicBool::new(false));
+            for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+                signal_hook::flag::register(signal, Arc::clone(&stop))?;
+            }
+            cargo_cleaner::daemon::run(&config, once, &stop)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Cache {
+            kind,
+            unused,
+            older_than,
+            remove,
+            clean,
+            yes,
+        }) => run_cache(
+            &config,
+            CacheArgs {
+                kind,
+                unused,
+                older_than,
+                remove,
+                clean,
+                yes,
+            },
+        ),
+        Some(Command::Stamp { project }) => run_stamp(&project),
+        Some(Command::Sweep { since_stamp }) => run_sweep(&config, &since_stamp),
+        Some(Command::Schedule { action }) => run_schedule(&args.config, action),
+        None => {
+            run_tui(config, args.free, args.min_free, args.min_size)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn run_list(config: &Config, format: OutputFormat, filter: FilterArgs) -> anyhow::Result<ExitCode> {
+    let (mut projects, errors) = filter.collect(config);
+    for project in &mut projects {
+        project.measure_units();
+    }
+    for err in errors {
+        eprintln!("warning: {err:#}");
+    }
+    write_projects(
+        &mut io::stdout().lock(),
+        &projects,
+        format,
+        config.size_unit,
+    )?;
+    Ok(if projects.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run_clean(config: &Config, yes: bool, filter: FilterArgs) -> anyhow::Result<ExitCode> {
+    let (mut projects, errors) = filter.collect(config);
+    for err in errors {
+        eprintln!("warning: {err:#}");
+    }
+    projects.retain(|it| {
+        let protected = config.is_protected(&it.project_path);
+        if protected {
+            eprintln!("skipping protected {}", it.project_path.display());
+        }
+        !protected
+    });
+    if projects.is_empty() {
+        println!("Nothing to clean");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // The scopes removing part of a target free less than its size
+    let options = config.clean_options();
+    let reclaimable = projects
+        .iter()
+        .map(|it| reclaimable_size(it, &options))
+        .collect::<Vec<_>>();
+    let total = reclaimable.iter().sum::<u64>();
+    if !yes {
+        ensure_terminal()?;
+        write_projects(
+            &mut io::stdout().lock(),
+            &projects,
+            OutputFormat::Table,
+            config.size_unit,
+        )?;
+        if !confirm(&format!(
+            "Clean {} of {} projects ({})?",
+            config.clean_scope.as_str(),
+            projects.len(),
+            config.size_unit.format(total)
+        ))? {
+            println!("Aborted");
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
+    let mut freed = 0;
+    let mut failed = 0;
+    for (project, reclaimable) in projects.iter().zip(reclaimable) {
+        let path = project.project_path.display();
+        if config.dry_run {
+            println!(
+                "would clean {path} ({})",
+                config.size_unit.format(reclaimable)
+            );
+            freed += reclaimable;
+            continue;
+        }
+        let result = clean_and_measure(project, &options);
+        match &result {
+            Ok(bytes) => {
+                println!("cleaned {path} ({})", config.size_unit.format(*bytes));
+                freed += bytes;
+            }
+            Err(err) => {
+                eprintln!("failed  {path}: {err:#}");
+                failed += 1;
+            }
+        }
+        let entry = JournalEntry::new(
+            "clean",
+            &project.project_path,
+            config.clean_scope.as_str(),
+            &result,
+        );
+        if let Err(err) = journal::append(&entry) {
+            eprintln!("warning: {err:#}");
+        }
+    }
+    println!(
+        "{} {} from {} projects{}",
+        if config.dry_run {
+            "Would free"
+        } else {
+            "Freed"
+        },
+        config.size_unit.format(freed),
+        projects.len() - failed,
+        if failed > 0 {
+            format!(", {failed} failed")
+        } else {
+            String::new()
+        }
+    );
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Refuse to ask for confirmation when nobody can answer
+fn ensure_terminal() -> anyhow::Result<()> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("refusing to clean without --yes when stdin is not a terminal");
+    }
+    Ok(())
+}
+
+/// Ask a yes/no `question` on the terminal, defaulting to no
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+struct CacheArgs {
+    kind: CacheKind,
+    unused: bool,
+    older_than: Option<Duration>,
+    remove: CacheRemoval,
+    clean: bool,
+    yes: bool,
+}
+
+fn run_cache(config: &Config, args: CacheArgs) -> anyhow::Result<ExitCode> {
+    let home = args
+        .kind
+        .home()
+        .with_context(|| format!("can not determine where the {} are", args.kind.as_str()))?;
+    let mut entries = args.kind.scan(&home);
+    if args.unused && args.kind.uses_references() {
+        // Projects without build output still need their dependencies
+        let filter = ProjectFilter {
+            min_size: Some(0),
+            ..Default::default()
+        };
+        let (projects, errors) = collect_projects(config, &filter);
+        let (references, reference_errors) = ProjectReferences::collect(
+            projects
+                .iter()
+                .filter(|it| !it.orphaned)
+                .map(|it| it.project_path.as_path()),
+        );
+        for err in errors.into_iter().chain(reference_errors) {
+            eprintln!("warning: {err:#}");
+        }
+        args.kind.mark_in_use(&mut entries, &references);
+    }
+    if args.unused {
+        let older_than = args.older_than.unwrap_or(config.cache_older_than.0);
+        let now = SystemTime::now();
+        entries.retain(|it| it.is_unused(older_than, now));
+    }
+    entries.retain(|entry| entry.parts.iter().any(|it| args.remove.includes(it.kind)));
+    entries.retain(|entry| {
+        if entry.protected {
+            eprintln!(
+                "skipping protected {} {} {}",
+                entry.group, entry.name, entry.version
+            );
+        }
+        !entry.protected
+    });
+
+    let total = entries.iter().map(|it| it.size(args.remove)).sum::<u64>();
+    if !args.clean {
+        write_cache_entries(
+            &mut io::stdout().lock(),
+            &entries,
+            args.remove,
+            config.size_unit,
+        )?;
+        println!(
+            "Reclaimable: {} from {} entries",
+            config.size_unit.format(total),
+            entries.len()
+        );
+        return Ok(if entries.is_empty() {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+    if entries.is_empty() {
+        println!("Nothing to clean");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if !args.yes {
+        ensure_terminal()?;
+        write_cache_entries(
+            &mut io::stdout().lock(),
+            &entries,
+            args.remove,
+            config.size_unit,
+        )?;
+        if !confirm(&format!(
+            "Remove {} entries ({}, {})?",
+            entries.len(),
+            args.kind.describe(args.remove),
+            config.size_unit.format(total)
+        ))? {
+            println!("Aborted");
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
+    let action = args.kind.describe(args.remove);
+    let mut freed = 0;
+    let mut failed = 0;
+    for entry in &entries {
+        let label = format!("{} {} {}", entry.group, entry.name, entry.version);
+        if config.dry_run {
+            println!(
+                "would remove {label} ({})",
+                config.size_unit.format(entry.size(args.remove))
+            );
+            freed += entry.size(args.remove);
+            continue;
+        }
+        let result = args.kind.remove(&home, entry, args.remove);
+        match &result {
+            Ok(bytes) => {
+                println!("removed {label} ({})", config.size_unit.format(*bytes));
+                freed += bytes;
+            }
+            Err(err) => {
+                eprintln!("failed  {label}: {err:#}");
+                failed += 1;
+            }
+        }
+        let journal_entry = JournalEntry::new("clean", entry.path(args.remove), &action, &result);
+        if let Err(err) = journal::append(&journal_entry) {
+            eprintln!("warning: {err:#}");
+        }
+    }
+    println!(
+        "{} {} from {} entries{}",
+        if config.dry_run {
+            "Would free"
+        } else {
+            "Freed"
+        },
+        config.size_unit.format(freed),
+        entries.len() - failed,
+        if failed > 0 {
+            format!(", {failed} failed")
+        } else {
+            String::new()
+        }
+    );
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run_stamp(project: &Path) -> anyhow::Result<ExitCode> {
+    anyhow::ensure!(project.is_dir(), "{} is not a directory", project.display());
+    let path = fingerprint::write_stamp(&resolve_target_dir(project), SystemTime::now())?;
+    println!("Stamped {}", path.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_sweep(config: &Config, project: &Path) -> anyhow::Result<ExitCode> {
+    let target_path = resolve_target_dir(project);
+    let stamp = fingerprint::read_stamp(&target_path)?;
+    let dirs = std::iter::once(target_path)
+        .chain(resolve_build_dir(project))
+        .collect::<Vec<_>>();
+    let plan = fingerprint::plan_sweep(&dirs, stamp);
+    let size = plan.size();
+    if config.dry_run {
+        for unit in &plan.units {
+            let profile = dirs
+                .iter()
+                .find_map(|it| unit.profile_dir.strip_prefix(it).ok())
+                .unwrap_or(&unit.profile_dir);
+            println!(
+                "would remove {} {}-{} ({})",
+                profile.display(),
+                unit.package,
+                unit.hash,
+                config.size_unit.format(unit.size)
+            );
+        }
+        for (path, size) in &plan.incremental {
+            println!(
+                "would remove {} ({})",
+                path.display(),
+                config.size_unit.format(*size)
+            );
+        }
+        println!(
+            "Would free {} from {} build units",
+            config.size_unit.format(size),
+            plan.units.len()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let result = plan.remove();
+    let project = project
+        .canonicalize()
+        .unwrap_or_else(|_| project.to_owned());
+    let entry = JournalEntry::new("sweep", &project, "since-stamp", &result);
+    if let Err(err) = journal::append(&entry) {
+        eprintln!("warning: {err:#}");
+    }
+    println!(
+        "Freed {} from {} build units",
+        config.size_unit.format(result?),
+        plan.units.len()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_schedule(config_args: &ConfigArgs, action: ScheduleCommand) -> anyhow::Result<ExitCode> {
+    let dir = schedule::unit_dir().context("can not determine the systemd user unit directory")?;
+    match action {
+        ScheduleCommand::Install {
+            every,
+            print,
+            filter,
+        } => {
+            let cwd = std::env::current_dir()?;
+            let mut clean_args = ["cleaner", "clean", "--yes"].map(String::from).to_vec();
+            clean_args.extend(config_args.to_args());
+            clean_args.extend(filter.to_args());
+            let schedule = Schedule {
+                every,
+                program: std::env::current_exe()?,
+                args: clean_args,
+                working_directory: cwd,
+                cargo_dir: schedule::cargo_dir(),
+            };
+            if print {
+                println!("# {SERVICE_NAME}\n{}", schedule.service_unit());
+                println!("# {TIMER_NAME}\n{}", schedule.timer_unit());
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            for path in schedule::write_units(&dir, &schedule)? {
+                println!("wrote {}", path.display());
+            }
+            match schedule::systemctl(&["daemon-reload"])
+                .and_then(|_| schedule::systemctl(&["enable", "--now", TIMER_NAME]))
+            {
+                Ok(_) => println!("enabled {TIMER_NAME}, cleaning {}", every.as_str()),
+                Err(err) => {
+                    eprintln!("warning: {err:#}");
+                    eprintln!("enable it with `systemctl --user enable --now {TIMER_NAME}`");
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        ScheduleCommand::Status => {
+            let (Ok(service), Ok(timer)) = (
+                std::fs::read_to_string(dir.join(SERVICE_NAME)),
+                std::fs::read_to_string(dir.join(TIMER_NAME)),
+            ) else {
+                println!("No schedule installed in {}", dir.display());
+                return Ok(ExitCode::FAILURE);
+            };
+            println!("Units:     {}", dir.display());
+            println!(
+                "Every:     {}",
+                schedule::unit_setting(&timer, "OnCalendar").unwrap_or("?")
+            );
+            println!(
+                "Command:   {}",
+                schedule::unit_setting(&service, "ExecStart").unwrap_or("?")
+            );
+            match schedule::systemctl(&["list-timers", "--all", TIMER_NAME]) {
+                Ok(timers) => print!("\n{timers}"),
+                Err(err) => eprintln!("warning: {err:#}"),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        ScheduleCommand::Remove => {
+            // The timer may already be gone or systemd may not be running
+            let _ = schedule::systemctl(&["disable", "--now", TIMER_NAME]);
+            let removed = schedule::remove_units(&dir)?;
+            if removed.is_empty() {
+                println!("No schedule installed in {}", dir.display());
+                return Ok(ExitCode::FAILURE);
+            }
+            for path in removed {
+                println!("removed {}", path.display());
+            }
+            let _ = schedule::systemctl(&["daemon-reload"]);
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn run_tui(
+    config: Config,
+    free: Option<u64>,
+    min_free: Option<u64>,
+    min_size: Option<u64>,
+) -> anyhow::Result<()> {
     // start find job
     let (notify_tx, notify_rx) = std::sync::mpsc::sync_channel(1);
 
-    let search_root = args
-        .search_root
-        .as_ref()
-        .map(PathBuf::from)
-        .unwrap_or_else(|| home_dir().expect("can not found HOME_DIR"));
-
-    let scan_workers = args.scan_workers.unwrap_or((num_cpus::get() - 1).max(1));
-    let (analysis_receiver, scan_progress) =
-        find_cargo_projects(search_root.as_path(), scan_workers, notify_tx.clone());
+    let (analysis_receiver, scan_progress) = find_cargo_projects(
+        &config.search_roots,
+        Excludes::new(&config.excludes),
+        config.scan_workers,
+        notify_tx.clone(),
+    );
 
     // setup terminal
     enable_raw_mode()?;
@@ -57,26 +930,73 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new(args.dry_run, notify_tx, scan_progress.clone());
+    let mut app = App::new(config.dry_run, notify_tx, scan_progress.clone());
+    app.apply_config(&config);
+    app.pending_goal = free;
+    app.pending_min_free = min_free;
+    app.filter.min_size = min_size;
+    for kind in [
+        CacheKind::Registry,
+        CacheKind::Git,
+        CacheKind::Binaries,
+        CacheKind::Toolchains,
+    ] {
+        if let Some(home) = kind.home().filter(|it| it.is_dir()) {
+            app.add_cache_tab(kind, home);
+        }
+    }
     let items = Arc::clone(&app.items);
+    let known_projects = Arc::clone(&app.known_projects);
+    let scan_finished = Arc::clone(&app.scan_finished);
+    let filled_columns = Arc::clone(&app.filled_columns);
 
     std::thread::spawn(move || {
         for analysis in analysis_receiver {
             match analysis {
-                Ok(analysis) => {
-                    if analysis.size > 0 {
-                        let mut items = items.write();
-                        let insert_index = items
-                            .binary_search_by_key(&std::cmp::Reverse(analysis.size), |it| {
-                                std::cmp::Reverse(it.size)
-                            })
-                            .unwrap_or_else(|it| it);
-                        items.insert(insert_index, analysis);
+                Ok(mut analysis) => {
+                    // Reading the details touches the disk, the UI keeps drawing meanwhile
+                    let columns = filled_columns.read().clone();
+                    analysis.fill_columns(&columns);
+                    let mut items = items.write();
+                    // Columns enabled meanwhile were only filled for the listed projects
+                    let added = filled_columns
+                        .read()
+                        .iter()
+                        .filter(|it| !columns.contains(it))
+                        .copied()
+                        .collect::<Vec<_>>();
+                    analysis.fill_columns(&added);
+                    // A shared target directory may be found before the projects using it
+                    if analysis.orphaned {
+                        if items
+                            .iter()
+                            .any(|it| it.artifact_dirs().contains(&analysis.target_path))
+                        {
+                            continue;
+                        }
+                    } else {
+                        known_projects.write().push(analysis.project_path.clone());
+                        let dirs = analysis.artifact_dirs();
+                        items.retain(|it| !it.orphaned || !dirs.contains(&it.target_path));
                     }
+                    // Projects without build output are kept for the view of all projects.
+                    // The app sorts the new project in.
+                    items.push(analysis);
                 }
                 Err(_err) => {}
             }
         }
+        *scan_finished.write() = true;
+
+        // Only some clean scopes show the sizes of single build units, which take another pass
+        // over every target, so they are measured after the list is complete
+        let projects = items.read().clone();
+        for mut project in projects {
+            let sizes = project.measure_units();
+            if let Some(it) = items.write().iter_mut().find(|it| it.id == project.id) {
+                it.unit_sizes = Some(sizes);
+            }
+        }
     });
     let res = run_app(&mut terminal, app, notify_rx);
 
@@ -108,7 +1028,7 @@ fn run_app(
         tui.draw(|f| ui(f, &mut app))?;
 
         match tui.read_event()? {
-            Event::AsyncUpdate => {}
+            Event::AsyncUpdate => app.on_async_update(),
             Event::Parent(ev) => {
                 if let CrosstermEvent::Key(key) = ev {
                     if app.handle_key(key.code).is_none() {
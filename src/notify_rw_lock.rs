@@ -31,7 +31,7 @@ impl<T> NotifyRwLock<T> {
         let mut s = self.state.load(Ordering::Relaxed);
 
         loop {
-            if s % 2 == 0 {
+            if s.is_multiple_of(2) {
                 assert!(s < u32::MAX - 2, "too many readers");
                 match self.state.compare_exchange_weak(
                     s,
@@ -72,7 +72,7 @@ impl<T> NotifyRwLock<T> {
                     }
                 }
             }
-            if s % 2 == 0 {
+            if s.is_multiple_of(2) {
                 if let Err(e) =
                     self.state
                         .compare_exchange(s, s + 1, Ordering::Relaxed, Ordering::Relaxed)
@@ -155,7 +155,6 @@ impl<T> DerefMut for WriteGuard<'_, T> {
 mod tests {
     use super::*;
     use std::hint::black_box;
-    use std::time::Instant;
 
     #[test]
     fn add_list() {
@@ -163,7 +162,6 @@ mod tests {
         let waiter_list = NotifyRwLock::new(tx, Vec::new());
         black_box(&waiter_list);
 
-        let start = Instant::now();
         std::thread::scope(|s| {
             let t1 = s.spawn({
                 || {
@@ -185,7 +183,7 @@ mod tests {
             });
             let t3 = s.spawn({
                 || {
-                    for i in 0..1000 {
+                    for _ in 0..1000 {
                         let c = waiter_list.read();
                         black_box(&c);
                     }
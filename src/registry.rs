@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::cargo_home::{measure, read_dir_entries, CacheEntry, CachePart, PartKind};
+use crate::lockfile::LockedPackage;
+
+/// Version used for the entry holding the index of a registry
+pub const INDEX_VERSION: &str = "(index)";
+
+/// Split a `<name>-<version>` directory or archive stem of the registry cache. Crate names may
+/// contain dashes themselves, so the version starts at the first dash followed by a semver.
+pub fn split_name_version(stem: &str) -> Option<(&str, &str)> {
+    static SEMVER: OnceLock<Regex> = OnceLock::new();
+    let semver = SEMVER.get_or_init(|| {
+        Regex::new(r"^\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$").unwrap()
+    });
+    stem.match_indices('-')
+        .map(|(index, _)| (&stem[..index], &stem[index + 1..]))
+        .find(|(name, version)| !name.is_empty() && semver.is_match(version))
+}
+
+/// Analyze `$CARGO_HOME/registry`: one entry per registry index and one per crate version with
+/// its downloaded archive and extracted sources, largest first
+pub fn scan_registry(cargo_home: &Path) -> Vec<CacheEntry> {
+    let registry = cargo_home.join("registry");
+    let mut entries = vec![];
+
+    for (group, path) in read_dir_entries(&registry.join("index")) {
+        let (size, last_used) = measure(&path);
+        entries.push(CacheEntry {
+            id: Uuid::new_v4(),
+            group,
+            name: String::new(),
+            version: INDEX_VERSION.to_string(),
+            parts: vec![CachePart {
+                kind: PartKind::Index,
+                path,
+                size,
+            }],
+            last_used,
+            in_use: None,
+            protected: false,
+        });
+    }
+
+    // Archives and sources of the same version are merged into one entry
+    let mut versions = BTreeMap::<(String, String, String), CacheEntry>::new();
+    for (kind, dir) in [(PartKind::Archive, "cache"), (PartKind::Source, "src")] {
+        for (group, group_path) in read_dir_entries(&registry.join(dir)) {
+            for (file_name, path) in read_dir_entries(&group_path) {
+                let stem = match kind {
+                    PartKind::Archive => match file_name.strip_suffix(".crate") {
+                        Some(stem) => stem,
+                        None => continue,
+                    },
+                    _ => &file_name,
+                };
+                let Some((name, version)) = split_name_version(stem) else {
+                    continue;
+                };
+                let (size, last_used) = measure(&path);
+                let entry = versions
+                    .entry((group.clone(), name.to_string(), version.to_string()))
+                    .or_insert_with(|| CacheEntry {
+                        id: Uuid::new_v4(),
+                        group: group.clone(),
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        parts: vec![],
+                        last_used: SystemTime::UNIX_EPOCH,
+                        in_use: None,
+                        protected: false,
+                    });
+                entry.parts.push(CachePart { kind, path, size });
+                entry.last_used = entry.last_used.max(last_used);
+            }
+        }
+    }
+    entries.extend(versions.into_values());
+
+    entries.sort_by_key(|it| std::cmp::Reverse(it.parts.iter().map(|it| it.size).sum::<u64>()));
+    entries
+}
+
+/// Mark crate versions as used when any of the locked `packages` is that version from a
+/// registry. Registries are not told apart, so a version locked from one registry keeps the same
+/// version of every registry.
+pub fn mark_in_use(entries: &mut [CacheEntry], packages: &HashSet<LockedPackage>) {
+    let locked = packages
+        .iter()
+        .filter(|it| it.is_from_registry())
+        .map(|it| (it.name.as_str(), it.version.as_str()))
+        .collect::<HashSet<_>>();
+    for entry in entries.iter_mut().filter(|it| it.version != INDEX_VERSION) {
+        entry.in_use = Some(locked.contains(&(entry.name.as_str(), entry.version.as_str())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo_home::CacheRemoval;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn names_and_versions_are_split() {
+        assert_eq!(
+            split_name_version("serde-1.0.217"),
+            Some(("serde", "1.0.217"))
+        );
+        assert_eq!(
+            split_name_version("wasm-bindgen-0.2.99"),
+            Some(("wasm-bindgen", "0.2.99"))
+        );
+        assert_eq!(
+            split_name_version("foo-2d-1.0.0-beta.1+build"),
+            Some(("foo-2d", "1.0.0-beta.1+build"))
+        );
+        assert_eq!(split_name_version("CACHEDIR.TAG"), None);
+    }
+
+    #[test]
+    fn archives_and_sources_are_merged() {
+        let home = TempDir::new("home");
+        let registry = home.join("registry");
+        let group = "index.crates.io-1949cf8c6b5b557f";
+        std::fs::create_dir_all(registry.join("index").join(group)).unwrap();
+        std::fs::create_dir_all(registry.join("cache").join(group)).unwrap();
+        std::fs::create_dir_all(registry.join("src").join(group).join("serde-1.0.0")).unwrap();
+        std::fs::write(
+            registry.join("cache").join(group).join("serde-1.0.0.crate"),
+            [0; 10],
+        )
+        .unwrap();
+        std::fs::write(
+            registry
+                .join("src")
+                .join(group)
+                .join("serde-1.0.0")
+                .join("lib.rs"),
+            [0; 100],
+        )
+        .unwrap();
+
+        let entries = scan_registry(&home);
+        assert_eq!(entries.len(), 2);
+        let serde = &entries[0];
+        assert_eq!(
+            (serde.name.as_str(), serde.version.as_str()),
+            ("serde", "1.0.0")
+        );
+        assert_eq!(serde.size(CacheRemoval::Sources), 100);
+        assert_eq!(serde.size(CacheRemoval::Archives), 10);
+        assert_eq!(entries[1].version, INDEX_VERSION);
+        // The index is only removed together with sources and archives
+        let index = &entries[1];
+        assert!(!index
+            .parts
+            .iter()
+            .any(|it| CacheRemoval::Sources.includes(it.kind)));
+        assert!(!index
+            .parts
+            .iter()
+            .any(|it| CacheRemoval::Archives.includes(it.kind)));
+        assert!(index
+            .parts
+            .iter()
+            .all(|it| CacheRemoval::All.includes(it.kind)));
+
+        let mut entries = entries;
+        let serde_lock = LockedPackage {
+            name: "serde".to_string(),
+            version: "1.0.0".to_string(),
+            source: Some("sparse+https://index.crates.io/".to_string()),
+        };
+        mark_in_use(&mut entries, &HashSet::from([serde_lock]));
+        assert_eq!(entries[0].in_use, Some(true));
+        assert_eq!(entries[1].in_use, None);
+        mark_in_use(&mut entries, &HashSet::new());
+        assert_eq!(entries[0].in_use, Some(false));
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use dirs::home_dir;
+
+/// Name of the generated systemd service running the cleanup
+pub const SERVICE_NAME: &str = "cargo-cleaner.service";
+/// Name of the generated systemd timer triggering the service
+pub const TIMER_NAME: &str = "cargo-cleaner.timer";
+
+/// How often a scheduled cleanup runs, named like the systemd calendar shorthands
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Every {
+    Hourly,
+    Daily,
+    #[default]
+    Weekly,
+    Monthly,
+}
+
+impl Every {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Every::Hourly => "hourly",
+            Every::Daily => "daily",
+            Every::Weekly => "weekly",
+            Every::Monthly => "monthly",
+        }
+    }
+}
+
+/// A periodic non-interactive `cargo cleaner clean` run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub every: Every,
+    /// The cargo-cleaner executable
+    pub program: PathBuf,
+    /// Arguments passed to `program`, starting with `cleaner clean`
+    pub args: Vec<String>,
+    /// Directory the cleanup runs in, so the same project-local config applies
+    pub working_directory: PathBuf,
+    /// Directory of the `cargo` run by the default deletion backend, put on the service's `PATH`
+    /// as the user manager's usually lacks `~/.cargo/bin`
+    pub cargo_dir: Option<PathBuf>,
+}
+
+impl Schedule {
+    /// Text of the `.service` unit running the cleanup once
+    pub fn service_unit(&self) -> String {
+        let command = std::iter::once(self.program.to_string_lossy().into_owned())
+            .chain(self.args.iter().cloned())
+            .map(|it| quote_arg(&it))
+            .collect::<Vec<_>>()
+            .join(" ");
+        // Unlike `ExecStart=`, `Environment=` expands no variables
+        let environment = self.cargo_dir.as_ref().map_or(String::new(), |dir| {
+            let path = format!(
+                "PATH={}:/usr/local/bin:/usr/bin:/bin",
+                dir.to_string_lossy()
+            );
+            format!(
+                "Environment=\"{}\"\n",
+                path.replace('%', "%%")
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+            )
+        });
+        format!(
+            "# Generated by `cargo cleaner schedule install`, rerun it instead of editing this file\n\
+             [Unit]\n\
+             Description=Clean cargo target directories\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             WorkingDirectory={}\n\
+             {environment}\
+             ExecStart={command}\n\
+             Nice=19\n\
+             IOSchedulingClass=idle\n",
+            // Paths are taken literally here, only specifiers need escaping
+            self.working_directory.to_string_lossy().replace('%', "%%")
+        )
+    }
+
+    /// Text of the `.timer` unit starting the service every `self.every`
+    pub fn timer_unit(&self) -> String {
+        format!(
+            "# Generated by `cargo cleaner schedule install`, rerun it instead of editing this file\n\
+             [Unit]\n\
+             Description=Clean cargo target directories {every}\n\
+             \n\
+             [Timer]\n\
+             OnCalendar={every}\n\
+             Persistent=true\n\
+             RandomizedDelaySec=1h\n\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n",
+            every = self.every.as_str()
+        )
+    }
+}
+
+/// Quote `arg` for an `ExecStart=` line. systemd splits on whitespace, understands double quotes
+/// with backslash escapes and expands `%` specifiers and `$` variables.
+fn quote_arg(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    if !escaped.is_empty()
+        && !escaped
+            .chars()
+            .any(|it| it.is_whitespace() || matches!(it, '"' | '\'' | '\\' | ';'))
+    {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Directory of the `cargo` found first on `PATH`
+pub fn cargo_dir() -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?).find(|it| it.join("cargo").is_file())
+}
+
+/// Directory of systemd user units, `$XDG_CONFIG_HOME/systemd/user` falling back to
+/// `~/.config/systemd/user`
+pub fn unit_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|it| it.is_absolute())
+        .or_else(|| home_dir().map(|it| it.join(".config")))
+        .map(|it| it.join("systemd").join("user"))
+}
+
+/// Write the service and timer of `schedule` to `dir`, returning the written paths
+pub fn write_units(dir: &Path, schedule: &Schedule) -> anyhow::Result<[PathBuf; 2]> {
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let service = dir.join(SERVICE_NAME);
+    let timer = dir.join(TIMER_NAME);
+    for (path, text) in [
+        (&service, schedule.service_unit()),
+        (&timer, schedule.timer_unit()),
+    ] {
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok([service, timer])
+}
+
+/// Delete the generated units from `dir`, returning the paths that existed
+pub fn remove_units(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut removed = vec![];
+    for path in [dir.join(TIMER_NAME), dir.join(SERVICE_NAME)] {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Value of the first `key=` line in a unit file
+pub fn unit_setting<'a>(unit: &'a str, key: &str) -> Option<&'a str> {
+    unit.lines()
+        .find_map(|it| it.strip_prefix(key)?.strip_prefix('='))
+        .map(str::trim)
+}
+
+/// Run `systemctl --user` with `args`, returning its standard output
+pub fn systemctl(args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .context("failed to run systemctl")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`systemctl --user {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn schedule() -> Schedule {
+        Schedule {
+            every: Every::Weekly,
+            program: PathBuf::from("/home/me/.cargo/bin/cargo-cleaner"),
+            args: [
+                "cleaner",
+                "clean",
+                "--yes",
+                "--older-than",
+                "30d",
+                "-r",
+                "/home/me/my work",
+            ]
+            .map(String::from)
+            .to_vec(),
+            working_directory: PathBuf::from("/home/me"),
+            cargo_dir: Some(PathBuf::from("/home/me/.cargo/bin")),
+        }
+    }
+
+    #[test]
+    fn units_invoke_clean_on_schedule() {
+        let schedule = schedule();
+        let service = schedule.service_unit();
+        assert_eq!(
+            unit_setting(&service, "ExecStart"),
+            Some(
+                "/home/me/.cargo/bin/cargo-cleaner cleaner clean --yes --older-than 30d \
+                 -r \"/home/me/my work\""
+            )
+        );
+        assert_eq!(unit_setting(&service, "Type"), Some("oneshot"));
+        assert_eq!(unit_setting(&service, "WorkingDirectory"), Some("/home/me"));
+        // `cargo clean` is found although the user manager's `PATH` lacks `~/.cargo/bin`
+        assert_eq!(
+            unit_setting(&service, "Environment"),
+            Some("\"PATH=/home/me/.cargo/bin:/usr/local/bin:/usr/bin:/bin\"")
+        );
+
+        let timer = schedule.timer_unit();
+        assert_eq!(unit_setting(&timer, "OnCalendar"), Some("weekly"));
+        assert_eq!(unit_setting(&timer, "WantedBy"), Some("timers.target"));
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(quote_arg("--older-than"), "--older-than");
+        assert_eq!(quote_arg("50%"), "50%%");
+        assert_eq!(quote_arg("$HOME/*"), "$$HOME/*");
+        assert_eq!(quote_arg("a \"b\""), "\"a \\\"b\\\"\"");
+        assert_eq!(quote_arg(""), "\"\"");
+    }
+
+    #[test]
+    fn units_are_written_and_removed() {
+        let dir = TempDir::new("units");
+        let [service, timer] = write_units(&dir, &schedule()).unwrap();
+        assert!(std::fs::read_to_string(&service)
+            .unwrap()
+            .contains("ExecStart="));
+        assert!(timer.is_file());
+
+        assert_eq!(remove_units(&dir).unwrap(), vec![timer, service]);
+        assert!(remove_units(&dir).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+
+use crate::units::{parse_duration, parse_size};
+use crate::ProjectTargetAnalysis;
+
+/// Query typed into the search prompt of the TUI, e.g. `client size>1G age>30d`.
+///
+/// Plain words are matched fuzzily against the project path and name, words like `size>1G` are
+/// conditions on the target. A project has to match every word.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    text: String,
+    terms: Vec<String>,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Condition {
+    field: Field,
+    comparison: Comparison,
+    /// Bytes for sizes, seconds for the age
+    value: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Size,
+    AllocatedSize,
+    /// Time since the target was last modified
+    Age,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Field {
+    const ALL: [Field; 3] = [Field::Size, Field::AllocatedSize, Field::Age];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Field::Size => "size",
+            Field::AllocatedSize => "allocated",
+            Field::Age => "age",
+        }
+    }
+
+    fn parse_value(self, value: &str) -> anyhow::Result<u64> {
+        match self {
+            Field::Size | Field::AllocatedSize => parse_size(value),
+            Field::Age => Ok(parse_duration(value)?.as_secs()),
+        }
+    }
+
+    fn value_of(self, project: &ProjectTargetAnalysis, now: SystemTime) -> u64 {
+        match self {
+            Field::Size => project.size,
+            Field::AllocatedSize => project.allocated_size,
+            Field::Age => now
+                .duration_since(project.last_modified)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+impl Comparison {
+    // Two character operators first, so `>=` is not read as `>` followed by `=1G`
+    const ALL: [(&'static str, Comparison); 5] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+        (">", Comparison::Greater),
+    ];
+
+    fn holds(self, actual: u64, value: u64) -> bool {
+        match self {
+            Comparison::Less => actual < value,
+            Comparison::LessOrEqual => actual <= value,
+            Comparison::Equal => actual == value,
+            Comparison::GreaterOrEqual => actual >= value,
+            Comparison::Greater => actual > value,
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.conditions.is_empty()
+    }
+
+    pub fn matches(&self, project: &ProjectTargetAnalysis, now: SystemTime) -> bool {
+        let path = project.project_path.to_string_lossy();
+        let name = project.project_name.as_deref().unwrap_or_default();
+        let terms_ok = self
+            .terms
+            .iter()
+            .all(|term| fuzzy_matches(term, &path) || fuzzy_matches(term, name));
+        let conditions_ok = self.conditions.iter().all(|condition| {
+            let actual = condition.field.value_of(project, now);
+            condition.comparison.holds(actual, condition.value)
+        });
+        terms_ok && conditions_ok
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut query = SearchQuery {
+            text: s.trim().to_string(),
+            ..Default::default()
+        };
+        for word in s.split_whitespace() {
+            match parse_condition(word) {
+                Some(condition) => query.conditions.push(condition?),
+                None => query.terms.push(word.to_string()),
+            }
+        }
+        Ok(query)
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The condition `word` spells out, `None` when it does not start with a field and an operator
+fn parse_condition(word: &str) -> Option<anyhow::Result<Condition>> {
+    Field::ALL.into_iter().find_map(|field| {
+        let rest = word.strip_prefix(field.as_str())?;
+        let (operator, comparison) = Comparison::ALL
+            .into_iter()
+            .find(|(operator, _)| rest.starts_with(operator))?;
+        let value = &rest[operator.len()..];
+        Some(match value {
+            "" => Err(anyhow!("missing value in `{word}`")),
+            value => field.parse_value(value).map(|value| Condition {
+                field,
+                comparison,
+                value,
+            }),
+        })
+    })
+}
+
+/// Whether the characters of `pattern` appear in `text` in the same order. Case is ignored unless
+/// the pattern contains upper case letters.
+pub fn fuzzy_matches(pattern: &str, text: &str) -> bool {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let mut text = text.chars();
+    pattern.chars().all(|expected| {
+        text.any(|it| match case_sensitive {
+            true => it == expected,
+            false => it.to_lowercase().eq(expected.to_lowercase()),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::GIB_SIZE;
+
+    #[test]
+    fn fuzzy_terms_match_in_order() {
+        assert!(fuzzy_matches("clnr", "cargo-cleaner"));
+        assert!(fuzzy_matches("CC", "Cargo-Cleaner"));
+        assert!(!fuzzy_matches("CC", "cargo-cleaner"));
+        assert!(!fuzzy_matches("rnc", "cargo-cleaner"));
+    }
+
+    #[test]
+    fn words_and_conditions_must_all_match() {
+        let now = SystemTime::now();
+        let project = ProjectTargetAnalysis {
+            project_path: PathBuf::from("/work/clientA/api"),
+            project_name: Some("api-server".to_string()),
+            size: 2 * GIB_SIZE,
+            last_modified: now - Duration::from_secs(40 * 24 * 60 * 60),
+            ..ProjectTargetAnalysis::analyze_orphaned(Path::new("/nonexistent/target"))
+        };
+        let matches = |query: &str| query.parse::<SearchQuery>().unwrap().matches(&project, now);
+
+        assert!(matches("clientA srv"));
+        assert!(matches("size>1G age>30d"));
+        assert!(matches("size>=2GiB age<=6w"));
+        assert!(!matches("size<1G"));
+        assert!(!matches("clientB size>1G"));
+        assert!("size>".parse::<SearchQuery>().is_err());
+        assert!("age>30".parse::<SearchQuery>().is_err());
+        assert!("size"
+            .parse::<SearchQuery>()
+            .is_ok_and(|it| it.terms == ["size"]));
+    }
+}
@@ -0,0 +1,196 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::ProjectTargetAnalysis;
+
+/// Attribute the project list is ordered by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Path,
+    Name,
+    LastModified,
+    /// Release of the compiler the target was last built with
+    BuiltWith,
+    AllocatedSize,
+    TargetPath,
+    GitBranch,
+    Toolchain,
+    WorkspaceMembers,
+}
+
+impl SortKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Size => "size",
+            SortKey::Path => "path",
+            SortKey::Name => "name",
+            SortKey::LastModified => "last-modified",
+            SortKey::BuiltWith => "built-with",
+            SortKey::AllocatedSize => "allocated-size",
+            SortKey::TargetPath => "target-path",
+            SortKey::GitBranch => "git-branch",
+            SortKey::Toolchain => "toolchain",
+            SortKey::WorkspaceMembers => "workspace-members",
+        }
+    }
+
+    /// Sizes, timestamps, releases and counts are most useful biggest/newest first, names
+    /// alphabetically
+    fn default_descending(self) -> bool {
+        matches!(
+            self,
+            SortKey::Size
+                | SortKey::LastModified
+                | SortKey::BuiltWith
+                | SortKey::AllocatedSize
+                | SortKey::WorkspaceMembers
+        )
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "size" => Ok(SortKey::Size),
+            "path" => Ok(SortKey::Path),
+            "name" => Ok(SortKey::Name),
+            "last-modified" | "modified" | "age" => Ok(SortKey::LastModified),
+            "built-with" | "compiler" => Ok(SortKey::BuiltWith),
+            "allocated-size" => Ok(SortKey::AllocatedSize),
+            "target-path" => Ok(SortKey::TargetPath),
+            "git-branch" => Ok(SortKey::GitBranch),
+            "toolchain" => Ok(SortKey::Toolchain),
+            "workspace-members" => Ok(SortKey::WorkspaceMembers),
+            _ => Err(anyhow!(
+                "unknown sort key `{s}` (expected size, path, name, last-modified, built-with, \
+                 allocated-size, target-path, git-branch, toolchain or workspace-members)"
+            )),
+        }
+    }
+}
+
+/// Sort key plus direction, written as `<key>[:asc|:desc]` (e.g. `size:desc`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Size,
+            descending: true,
+        }
+    }
+}
+
+impl SortOrder {
+    pub fn compare(&self, a: &ProjectTargetAnalysis, b: &ProjectTargetAnalysis) -> Ordering {
+        let ordering = match self.key {
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Path => a.project_path.cmp(&b.project_path),
+            SortKey::Name => a.project_name.cmp(&b.project_name),
+            SortKey::LastModified => a.last_modified.cmp(&b.last_modified),
+            SortKey::BuiltWith => release(a).cmp(&release(b)),
+            SortKey::AllocatedSize => a.allocated_size.cmp(&b.allocated_size),
+            SortKey::TargetPath => a.target_path.cmp(&b.target_path),
+            SortKey::GitBranch => a.git_branch.cmp(&b.git_branch),
+            SortKey::Toolchain => a.toolchain.cmp(&b.toolchain),
+            SortKey::WorkspaceMembers => a.workspace_members.cmp(&b.workspace_members),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// The key after the current one among `keys` in its default direction, the first key when
+    /// the current one is not among them
+    pub fn next_key(self, keys: &[SortKey]) -> Self {
+        let index = keys.iter().position(|it| *it == self.key);
+        let Some(&key) = keys.get(index.map_or(0, |it| (it + 1) % keys.len())) else {
+            return self;
+        };
+        Self {
+            key,
+            descending: key.default_descending(),
+        }
+    }
+
+    /// The same key in the other direction
+    pub fn reversed(self) -> Self {
+        Self {
+            descending: !self.descending,
+            ..self
+        }
+    }
+}
+
+/// Numeric parts of the release a project was built with, e.g. `[1, 84, 0]` for
+/// `1.84.0 (9fc6b4312 2025-01-07)`, so `1.100` sorts after `1.84`
+fn release(project: &ProjectTargetAnalysis) -> Option<Vec<u64>> {
+    let version = project.built_with.as_deref()?.split([' ', '-']).next()?;
+    Some(
+        version
+            .split('.')
+            .filter_map(|it| it.parse().ok())
+            .collect(),
+    )
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, direction) = match s.split_once(':') {
+            Some((key, direction)) => (key.parse::<SortKey>()?, Some(direction)),
+            None => (s.parse::<SortKey>()?, None),
+        };
+        let descending = match direction {
+            None => key.default_descending(),
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => {
+                return Err(anyhow!(
+                    "unknown sort direction `{other}` (expected asc or desc)"
+                ))
+            }
+        };
+        Ok(Self { key, descending })
+    }
+}
+
+impl TryFrom<String> for SortOrder {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SortOrder> for String {
+    fn from(value: SortOrder) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.key.as_str(),
+            if self.descending { "desc" } else { "asc" }
+        )
+    }
+}
@@ -0,0 +1,40 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory below the system's temporary directory for test fixtures. It is removed
+/// with everything in it when dropped, so a failing assertion does not leave it behind.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create `cargo-cleaner-<name>-<uuid>` in the temporary directory
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("cargo-cleaner-{name}-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn as_path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
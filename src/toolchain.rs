@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use dirs::home_dir;
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::cargo_home::{measure, read_dir_entries, CacheEntry, CachePart, PartKind};
+
+/// Location of rustup's home, `$RUSTUP_HOME` falling back to `~/.rustup`
+pub fn rustup_home() -> Option<PathBuf> {
+    std::env::var_os("RUSTUP_HOME")
+        .map(PathBuf::from)
+        .filter(|it| it.is_absolute())
+        .or_else(|| home_dir().map(|it| it.join(".rustup")))
+}
+
+/// A toolchain as named by rustup, e.g. `nightly-2024-05-01-x86_64-unknown-linux-gnu`, or as
+/// pinned by a project, where the date and host may be left out
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Toolchain {
+    /// `stable`, `beta`, `nightly` or a version like `1.80` or `1.80.1`
+    pub channel: String,
+    pub date: Option<String>,
+    pub host: Option<String>,
+}
+
+impl Toolchain {
+    /// Parse a toolchain name. Names that are not a channel, like custom toolchains, become a
+    /// channel of their own.
+    pub fn parse(name: &str) -> Self {
+        static NAME: OnceLock<Regex> = OnceLock::new();
+        let regex = NAME.get_or_init(|| {
+            Regex::new(
+                r"^(stable|beta|nightly|\d+\.\d+(?:\.\d+)?(?:-beta(?:\.\d+)?)?)(?:-(\d{4}-\d{2}-\d{2}))?(?:-(.+))?$",
+            )
+            .unwrap()
+        });
+        match regex.captures(name.trim()) {
+            Some(captures) => Toolchain {
+                channel: captures[1].to_string(),
+                date: captures.get(2).map(|it| it.as_str().to_string()),
+                host: captures.get(3).map(|it| it.as_str().to_string()),
+            },
+            None => Toolchain {
+                channel: name.trim().to_string(),
+                date: None,
+                host: None,
+            },
+        }
+    }
+
+    /// Whether the pinned toolchain `self` is satisfied by the `installed` one. A version without
+    /// patch level like `1.80` matches every `1.80.x`.
+    pub fn matches(&self, installed: &Toolchain) -> bool {
+        (installed.channel == self.channel
+            || installed.channel.starts_with(&format!("{}.", self.channel)))
+            && (self.date.is_none() || self.date == installed.date)
+            && (self.host.is_none() || self.host == installed.host)
+    }
+}
+
+impl std::fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.channel)?;
+        for part in [&self.date, &self.host].into_iter().flatten() {
+            write!(f, "-{part}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `rust-toolchain.toml` or `rust-toolchain` file rustup uses for `project_path`, the nearest
+/// one in the project or its ancestors
+fn find_toolchain_file(project_path: &Path) -> Option<PathBuf> {
+    project_path.ancestors().find_map(|dir| {
+        ["rust-toolchain.toml", "rust-toolchain"]
+            .into_iter()
+            .map(|it| dir.join(it))
+            .find(|it| it.is_file())
+    })
+}
+
+/// Toolchain pinned for `project_path` by its `rust-toolchain` file
+pub fn pinned_toolchain(project_path: &Path) -> Option<Toolchain> {
+    read_toolchain_file(&find_toolchain_file(project_path)?)
+        .ok()
+        .flatten()
+}
+
+/// Channel of a `rust-toolchain` file, either in the legacy format holding just the channel or
+/// in the toml format with a `[toolchain]` table
+fn read_toolchain_file(path: &Path) -> anyhow::Result<Option<Toolchain>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    if let Ok(table) = toml::from_str::<toml::Table>(&text) {
+        let channel = table
+            .get("toolchain")
+            .and_then(|it| it.get("channel"))
+            .and_then(|it| it.as_str());
+        return Ok(channel.map(Toolchain::parse));
+    }
+    match text.trim() {
+        channel if !channel.is_empty() && !channel.contains(['\n', '=']) => {
+            Ok(Some(Toolchain::parse(channel)))
+        }
+        _ => anyhow::bail!("failed to parse {}", path.display()),
+    }
+}
+
+/// `rust-version` of the package or of the workspace in the manifest of `project_path`
+fn read_rust_version(project_path: &Path) -> anyhow::Result<Option<Toolchain>> {
+    let path = project_path.join("Cargo.toml");
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let manifest: toml::Table =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    let package = manifest.get("package");
+    let workspace_package = manifest.get("workspace").and_then(|it| it.get("package"));
+    let rust_version = [package, workspace_package]
+        .into_iter()
+        .flatten()
+        .find_map(|it| it.get("rust-version")?.as_str());
+    Ok(rust_version.map(Toolchain::parse))
+}
+
+/// Toolchains pinned by `rust-toolchain` files of the projects (the nearest one, like rustup),
+/// the `rust-version` of their manifests and rustup's default toolchain and directory overrides
+pub fn collect_toolchain_pins<'a>(
+    project_paths: impl IntoIterator<Item = &'a Path>,
+    rustup_home: Option<&Path>,
+) -> (HashSet<Toolchain>, Vec<anyhow::Error>) {
+    let mut pins = HashSet::new();
+    let mut errors = vec![];
+    let mut toolchain_files = HashSet::new();
+    for project_path in project_paths {
+        toolchain_files.extend(find_toolchain_file(project_path));
+        match read_rust_version(project_path) {
+            Ok(pin) => pins.extend(pin),
+            Err(err) => errors.push(err),
+        }
+    }
+    for path in toolchain_files {
+        match read_toolchain_file(&path) {
+            Ok(pin) => pins.extend(pin),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if let Some(settings) = rustup_home.and_then(read_settings) {
+        let default = settings.get("default_toolchain").and_then(|it| it.as_str());
+        let overrides = settings
+            .get("overrides")
+            .and_then(|it| it.as_table())
+            .into_iter()
+            .flat_map(|it| it.values())
+            .filter_map(|it| it.as_str());
+        pins.extend(default.into_iter().chain(overrides).map(Toolchain::parse));
+    }
+    (pins, errors)
+}
+
+/// rustup's `settings.toml`, holding the default toolchain and the directory overrides
+fn read_settings(rustup_home: &Path) -> Option<toml::Table> {
+    let text = std::fs::read_to_string(rustup_home.join("settings.toml")).ok()?;
+    toml::from_str(&text).ok()
+}
+
+/// Toolchains rustup runs in `cwd`, which must never be removed: `$RUSTUP_TOOLCHAIN`, the
+/// directory override of `cwd` or its nearest ancestor, the `rust-toolchain` file and the
+/// default toolchain
+pub fn active_toolchains(cwd: Option<&Path>, rustup_home: &Path) -> HashSet<Toolchain> {
+    let mut active = HashSet::new();
+    active.extend(
+        std::env::var("RUSTUP_TOOLCHAIN")
+            .ok()
+            .filter(|it| !it.is_empty())
+            .map(|it| Toolchain::parse(&it)),
+    );
+    active.extend(cwd.and_then(pinned_toolchain));
+    if let Some(settings) = read_settings(rustup_home) {
+        let overrides = settings.get("overrides").and_then(|it| it.as_table());
+        let dir_override = overrides.zip(cwd).and_then(|(overrides, cwd)| {
+            cwd.ancestors()
+                .find_map(|dir| overrides.get(dir.to_str()?)?.as_str())
+        });
+        let default = settings.get("default_toolchain").and_then(|it| it.as_str());
+        active.extend(
+            dir_override
+                .into_iter()
+                .chain(default)
+                .map(Toolchain::parse),
+        );
+    }
+    active
+}
+
+/// Analyze `$RUSTUP_HOME/toolchains`: one entry per installed toolchain, largest first. Linked
+/// custom toolchains live elsewhere and are left out.
+pub fn scan_toolchains(rustup_home: &Path) -> Vec<CacheEntry> {
+    let mut entries = read_dir_entries(&rustup_home.join("toolchains"))
+        .into_iter()
+        .filter(|(_, path)| {
+            path.symlink_metadata()
+                .is_ok_and(|it| it.is_dir() && !it.is_symlink())
+        })
+        .map(|(name, path)| {
+            let toolchain = Toolchain::parse(&name);
+            let (size, last_used) = measure(&path);
+            let mut parts = vec![CachePart {
+                kind: PartKind::Toolchain,
+                path,
+                size,
+            }];
+            // rustup's record of the installed manifest, removed by `rustup toolchain uninstall`
+            let update_hash = rustup_home.join("update-hashes").join(&name);
+            if update_hash.is_file() {
+                parts.push(CachePart {
+                    kind: PartKind::Toolchain,
+                    size: measure(&update_hash).0,
+                    path: update_hash,
+                });
+            }
+            CacheEntry {
+                id: Uuid::new_v4(),
+                group: toolchain.host.unwrap_or_default(),
+                name: toolchain.channel,
+                version: toolchain.date.unwrap_or_default(),
+                parts,
+                last_used,
+                in_use: None,
+                protected: false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by_key(|it| std::cmp::Reverse(it.parts.iter().map(|it| it.size).sum::<u64>()));
+    entries
+}
+
+/// Whether the toolchain of `entry` satisfies any of the `pins`
+pub fn is_pinned(entry: &CacheEntry, pins: &HashSet<Toolchain>) -> bool {
+    let installed = Toolchain {
+        channel: entry.name.clone(),
+        date: Some(entry.version.clone()).filter(|it| !it.is_empty()),
+        host: Some(entry.group.clone()).filter(|it| !it.is_empty()),
+    };
+    pins.iter().any(|it| it.matches(&installed))
+}
+
+/// Mark toolchains as used when any of the `pins` is satisfied by them or rustup runs them
+pub fn mark_in_use(entries: &mut [CacheEntry], pins: &HashSet<Toolchain>) {
+    for entry in entries.iter_mut() {
+        entry.in_use = Some(entry.protected || is_pinned(entry, pins));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn pins_match_installed_toolchains() {
+        let nightly = Toolchain::parse("nightly-2024-05-01-x86_64-unknown-linux-gnu");
+        assert_eq!(nightly.channel, "nightly");
+        assert_eq!(nightly.date.as_deref(), Some("2024-05-01"));
+        assert_eq!(nightly.host.as_deref(), Some("x86_64-unknown-linux-gnu"));
+        let version = Toolchain::parse("1.80.1-aarch64-apple-darwin");
+        assert_eq!(
+            (version.channel.as_str(), version.date.as_deref()),
+            ("1.80.1", None)
+        );
+
+        assert!(Toolchain::parse("nightly").matches(&nightly));
+        assert!(Toolchain::parse("nightly-2024-05-01").matches(&nightly));
+        assert!(!Toolchain::parse("nightly-2024-06-01").matches(&nightly));
+        assert!(Toolchain::parse("1.80").matches(&version));
+        assert!(!Toolchain::parse("1.8").matches(&version));
+        assert!(!Toolchain::parse("stable-x86_64-unknown-linux-gnu").matches(&version));
+    }
+
+    #[test]
+    fn projects_pin_toolchains() {
+        let root = TempDir::new("toolchain");
+        let (a, b) = (root.join("a"), root.join("b"));
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        std::fs::write(
+            a.join("Cargo.toml"),
+            "[package]\nname = \"a\"\nrust-version = \"1.75\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            a.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"nightly-2024-05-01\"\n",
+        )
+        .unwrap();
+        std::fs::write(b.join("Cargo.toml"), "[package]\nname = \"b\"\n").unwrap();
+        std::fs::write(root.join("rust-toolchain"), "beta\n").unwrap();
+        let rustup = root.join("rustup");
+        for name in [
+            "1.75.0-x86_64-unknown-linux-gnu",
+            "beta-x86_64-unknown-linux-gnu",
+            "stable-x86_64-unknown-linux-gnu",
+        ] {
+            std::fs::create_dir_all(rustup.join("toolchains").join(name)).unwrap();
+        }
+
+        let (pins, errors) = collect_toolchain_pins([a.as_path(), b.as_path()], Some(&rustup));
+        assert!(errors.is_empty());
+        assert_eq!(pins.len(), 3);
+        let mut entries = scan_toolchains(&rustup);
+        mark_in_use(&mut entries, &pins);
+        let in_use = |channel: &str| {
+            entries
+                .iter()
+                .find(|it| it.name == channel)
+                .and_then(|it| it.in_use)
+        };
+        assert_eq!(in_use("1.75.0"), Some(true));
+        assert_eq!(in_use("beta"), Some(true));
+        assert_eq!(in_use("stable"), Some(false));
+    }
+
+    #[test]
+    fn default_and_override_toolchains_are_active() {
+        let root = TempDir::new("active");
+        let (project, other) = (root.join("project"), root.join("other"));
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        let rustup = root.join("rustup");
+        for name in [
+            "1.75.0-x86_64-unknown-linux-gnu",
+            "beta-x86_64-unknown-linux-gnu",
+            "nightly-2024-05-01-x86_64-unknown-linux-gnu",
+            "stable-x86_64-unknown-linux-gnu",
+        ] {
+            std::fs::create_dir_all(rustup.join("toolchains").join(name)).unwrap();
+        }
+        let mut overrides = toml::Table::new();
+        overrides.insert(project.display().to_string(), "nightly-2024-05-01".into());
+        overrides.insert(other.display().to_string(), "beta".into());
+        let mut settings = toml::Table::new();
+        settings.insert(
+            "default_toolchain".into(),
+            "stable-x86_64-unknown-linux-gnu".into(),
+        );
+        settings.insert("overrides".into(), overrides.into());
+        std::fs::write(rustup.join("settings.toml"), settings.to_string()).unwrap();
+
+        let active = active_toolchains(Some(&project.join("src")), &rustup);
+        let entries = scan_toolchains(&rustup);
+        let is_active = |channel: &str| {
+            entries
+                .iter()
+                .any(|it| it.name == channel && is_pinned(it, &active))
+        };
+        assert!(is_active("stable"));
+        assert!(is_active("nightly"));
+        if std::env::var_os("RUSTUP_TOOLCHAIN").is_none() {
+            assert!(!is_active("beta"));
+            assert!(!is_active("1.75.0"));
+        }
+    }
+}
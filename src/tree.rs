@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::ProjectTargetAnalysis;
+
+/// Row of the project list, a project or, in the tree view, a directory grouping projects
+#[derive(Clone, Debug)]
+pub enum TreeRow {
+    Directory(DirectoryNode),
+    Project {
+        project: ProjectTargetAnalysis,
+        /// Path relative to the directory row it is listed under, the full path in the flat list
+        label: String,
+        depth: usize,
+    },
+}
+
+/// Directory of the tree view with the totals of every project below it
+#[derive(Clone, Debug)]
+pub struct DirectoryNode {
+    pub path: PathBuf,
+    /// Path relative to the parent directory row, spanning several levels when the directories
+    /// in between hold nothing else
+    pub label: String,
+    pub depth: usize,
+    pub collapsed: bool,
+    /// The projects below the directory in the order of the list
+    pub projects: Vec<Uuid>,
+    pub size: u64,
+    pub allocated_size: u64,
+    /// The most recent modification among the projects
+    pub last_modified: SystemTime,
+}
+
+/// Identity of a row that survives rebuilding the rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RowKey {
+    Project(Uuid),
+    Directory(PathBuf),
+}
+
+impl TreeRow {
+    pub fn key(&self) -> RowKey {
+        match self {
+            TreeRow::Directory(directory) => RowKey::Directory(directory.path.clone()),
+            TreeRow::Project { project, .. } => RowKey::Project(project.id),
+        }
+    }
+
+    /// How many directory rows the row is listed under
+    pub fn depth(&self) -> usize {
+        match self {
+            TreeRow::Directory(directory) => directory.depth,
+            TreeRow::Project { depth, .. } => *depth,
+        }
+    }
+
+    pub fn project(&self) -> Option<&ProjectTargetAnalysis> {
+        match self {
+            TreeRow::Directory(_) => None,
+            TreeRow::Project { project, .. } => Some(project),
+        }
+    }
+
+    /// The project of the row, or every project below the directory
+    pub fn project_ids(&self) -> Vec<Uuid> {
+        match self {
+            TreeRow::Directory(directory) => directory.projects.clone(),
+            TreeRow::Project { project, .. } => vec![project.id],
+        }
+    }
+}
+
+/// One row per project in the given order
+pub fn flat_rows(projects: Vec<ProjectTargetAnalysis>) -> Vec<TreeRow> {
+    projects
+        .into_iter()
+        .map(|project| TreeRow::Project {
+            label: project.project_path.to_string_lossy().into_owned(),
+            project,
+            depth: 0,
+        })
+        .collect()
+}
+
+/// The projects grouped by the directories they are in.
+///
+/// Directories come in the order of their first project, so the tree follows the sort order of
+/// `projects`. Only directories holding more than one project or directory get a row, and the
+/// rows below the directories in `collapsed` are left out.
+pub fn tree_rows(projects: &[ProjectTargetAnalysis], collapsed: &HashSet<PathBuf>) -> Vec<TreeRow> {
+    let mut root = Node::default();
+    for project in projects {
+        root.insert(project);
+    }
+    let mut rows = vec![];
+    root.emit(PathBuf::new(), PathBuf::new(), 0, collapsed, &mut rows);
+    rows
+}
+
+#[derive(Default)]
+struct Node<'a> {
+    children: Vec<(OsString, Node<'a>)>,
+    project: Option<&'a ProjectTargetAnalysis>,
+}
+
+impl<'a> Node<'a> {
+    fn insert(&mut self, project: &'a ProjectTargetAnalysis) {
+        let mut node = self;
+        for component in project.project_path.components() {
+            let name = component.as_os_str();
+            let index = match node.children.iter().position(|(it, _)| it == name) {
+                Some(index) => index,
+                None => {
+                    node.children.push((name.to_owned(), Node::default()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[index].1;
+        }
+        node.project = Some(project);
+    }
+
+    /// The projects in and below this directory
+    fn projects(&self) -> Vec<&'a ProjectTargetAnalysis> {
+        let mut projects = self.project.into_iter().collect::<Vec<_>>();
+        for (_, child) in &self.children {
+            projects.extend(child.projects());
+        }
+        projects
+    }
+
+    fn emit(
+        &self,
+        mut path: PathBuf,
+        mut label: PathBuf,
+        depth: usize,
+        collapsed: &HashSet<PathBuf>,
+        rows: &mut Vec<TreeRow>,
+    ) {
+        // A directory holding a single directory shares its row
+        let mut node = self;
+        while let (None, [(name, child)]) = (node.project, node.children.as_slice()) {
+            path.push(name);
+            label.push(name);
+            node = child;
+        }
+        if node.children.is_empty() {
+            if let Some(project) = node.project {
+                rows.push(TreeRow::Project {
+                    project: project.clone(),
+                    label: label.to_string_lossy().into_owned(),
+                    depth,
+                });
+            }
+            return;
+        }
+
+        let projects = node.projects();
+        let is_collapsed = collapsed.contains(&path);
+        rows.push(TreeRow::Directory(DirectoryNode {
+            label: label.to_string_lossy().into_owned(),
+            depth,
+            collapsed: is_collapsed,
+            projects: projects.iter().map(|it| it.id).collect(),
+            size: projects.iter().map(|it| it.size).sum(),
+            allocated_size: projects.iter().map(|it| it.allocated_size).sum(),
+            last_modified: projects
+                .iter()
+                .map(|it| it.last_modified)
+                .max()
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+            path: path.clone(),
+        }));
+        if is_collapsed {
+            return;
+        }
+        // A workspace root is listed first among its members
+        if let Some(project) = node.project {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            rows.push(TreeRow::Project {
+                project: project.clone(),
+                label: name.to_string_lossy().into_owned(),
+                depth: depth + 1,
+            });
+        }
+        for (name, child) in &node.children {
+            child.emit(
+                path.join(name),
+                PathBuf::from(name),
+                depth + 1,
+                collapsed,
+                rows,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn project(path: &str, size: u64) -> ProjectTargetAnalysis {
+        ProjectTargetAnalysis {
+            project_path: PathBuf::from(path),
+            size,
+            orphaned: false,
+            ..ProjectTargetAnalysis::analyze_orphaned(Path::new("/nonexistent/target"))
+        }
+    }
+
+    fn describe(rows: &[TreeRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                TreeRow::Directory(it) => {
+                    format!("{}{}/ {}", " ".repeat(it.depth), it.label, it.size)
+                }
+                TreeRow::Project { label, depth, .. } => format!("{}{label}", " ".repeat(*depth)),
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn projects_are_grouped_by_directory() {
+        let projects = [
+            project("/home/me/work/clientA/api", 5),
+            project("/home/me/oss/tool", 4),
+            project("/home/me/work/clientA/web", 3),
+            project("/home/me/work/clientB/shop/backend", 2),
+            project("/home/me/oss/tool/crates/macros", 1),
+        ];
+
+        let rows = tree_rows(&projects, &HashSet::new());
+        assert_eq!(
+            describe(&rows),
+            [
+                "/home/me/ 15",
+                " work/ 10",
+                "  clientA/ 8",
+                "   api",
+                "   web",
+                "  clientB/shop/backend",
+                " oss/tool/ 5",
+                "  tool",
+                "  crates/macros",
+            ]
+        );
+
+        let collapsed = [PathBuf::from("/home/me/work")].into_iter().collect();
+        let rows = tree_rows(&projects, &collapsed);
+        assert_eq!(
+            describe(&rows),
+            [
+                "/home/me/ 15",
+                " work/ 10",
+                " oss/tool/ 5",
+                "  tool",
+                "  crates/macros"
+            ]
+        );
+        assert_eq!(
+            rows[1].project_ids(),
+            [projects[0].id, projects[2].id, projects[3].id]
+        );
+    }
+}
@@ -1,48 +1,194 @@
+use clap::ValueEnum;
 use crossterm::event::KeyCode;
 use itertools::Itertools;
 use ratatui::prelude::*;
 use ratatui::widgets::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::mpsc::SyncSender;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
+use crate::breakdown::{self, BreakdownView, BREAKDOWN_COMMAND_KEY};
+use crate::cache_tab::{self, CacheTab};
+use crate::cargo_home::{CacheKind, ProjectReferences};
+use crate::clean::{
+    clean_and_measure, reclaimable_size, CleanOptions, CleanScope, DeletionBackend,
+};
+use crate::columns::Column;
+use crate::config::Config;
+use crate::disk::{DiskSpace, FileSystemSummary};
+use crate::filter::ProjectFilter;
+use crate::goal::{plan_free_space, plan_min_free};
+use crate::journal::{self, JournalEntry};
 use crate::notify_rw_lock::NotifyRwLock;
+use crate::search::SearchQuery;
+use crate::sort::{SortKey, SortOrder};
+use crate::tree::{flat_rows, tree_rows, RowKey, TreeRow};
+use crate::units::{format_age, parse_size, HumanDuration, SizeUnit};
 use crate::Progress;
-use crate::ProjectTargetAnalysis;
-use crate::GIB_SIZE;
+use crate::{ProjectTargetAnalysis, UnitSizes, ORPHANED_TARGET};
 
-const DELETE_COMMAND_KEY: char = 'd';
-const COLUMNS: usize = 3;
+pub(crate) const DELETE_COMMAND_KEY: char = 'd';
+const GOAL_COMMAND_KEY: char = 'f';
+const SCOPE_COMMAND_KEY: char = 'c';
+const SHOW_ALL_COMMAND_KEY: char = 'a';
+const SORT_COMMAND_KEY: char = 's';
+const REVERSE_SORT_COMMAND_KEY: char = 'S';
+const COLUMNS_COMMAND_KEY: char = 'C';
+const SEARCH_COMMAND_KEY: char = '/';
+const SELECT_ALL_COMMAND_KEY: char = 'A';
+const TREE_COMMAND_KEY: char = 't';
 
+/// A row of a table whose columns are chosen at runtime
 pub trait TableRow {
-    fn header() -> [Cell<'static>; COLUMNS];
-    fn cells(&self) -> [Cell; COLUMNS];
+    type Column: Copy;
+
+    /// Title of `column`, marked with its direction when `sort` orders by it
+    fn header(column: Self::Column, size_unit: SizeUnit, sort: SortOrder) -> Cell<'static>;
+    fn cell(&self, column: Self::Column, size_unit: SizeUnit) -> Cell<'_>;
+    fn width(column: Self::Column) -> Constraint;
 }
 
 impl TableRow for ProjectTargetAnalysis {
-    fn header() -> [Cell<'static>; COLUMNS] {
-        [
-            Cell::from("Project Path").style(Style::default().fg(Color::Yellow)),
-            Cell::from("Project Name").style(Style::default().fg(Color::Yellow)),
-            Cell::from("Size(GiB)").style(Style::default().fg(Color::Yellow)),
-        ]
-    }
-
-    fn cells(&self) -> [Cell; COLUMNS] {
-        [
-            Cell::from(self.project_path.to_str().unwrap()).style(Style::default()),
-            Cell::from(self.project_name.as_deref().unwrap_or("NOT FOUND NAME"))
-                .style(Style::default()),
-            Cell::from(format!("{:.2}GiB", self.size as f64 / (GIB_SIZE as f64)))
-                .style(Style::default()),
-        ]
+    type Column = Column;
+
+    fn header(column: Column, size_unit: SizeUnit, sort: SortOrder) -> Cell<'static> {
+        let title = match (column, size_unit) {
+            (Column::Size | Column::AllocatedSize, unit) if unit != SizeUnit::Auto => {
+                format!("{}({})", column.title(), unit.label())
+            }
+            _ => column.title().to_string(),
+        };
+        let title = match (sort.key == column.sort_key(), sort.descending) {
+            (true, true) => format!("{title} ▼"),
+            (true, false) => format!("{title} ▲"),
+            (false, _) => title,
+        };
+        Cell::from(title).style(Style::default().fg(Color::Yellow))
+    }
+
+    fn cell(&self, column: Column, size_unit: SizeUnit) -> Cell<'_> {
+        match column {
+            Column::Path => Cell::from(self.project_path.to_string_lossy()),
+            Column::Name => match self.orphaned {
+                true => Cell::from(ORPHANED_TARGET).style(Style::default().fg(Color::Gray)),
+                false => Cell::from(self.project_name.as_deref().unwrap_or("NOT FOUND NAME")),
+            },
+            Column::Size => Cell::from(size_unit.format(self.size)),
+            // A target that was never written to has no age
+            Column::Age if self.size == 0 => Cell::from(""),
+            Column::Age => {
+                let age = SystemTime::now()
+                    .duration_since(self.last_modified)
+                    .unwrap_or_default();
+                Cell::from(format_age(age))
+            }
+            Column::AllocatedSize => Cell::from(size_unit.format(self.allocated_size)),
+            Column::TargetPath => Cell::from(self.target_path.to_string_lossy()),
+            Column::GitBranch => Cell::from(self.git_branch.as_deref().unwrap_or_default()),
+            Column::Toolchain => Cell::from(self.toolchain.as_deref().unwrap_or_default()),
+            Column::WorkspaceMembers => Cell::from(
+                self.workspace_members
+                    .map(|it| it.to_string())
+                    .unwrap_or_default(),
+            ),
+            Column::BuiltWith => built_with_cell(self),
+        }
+    }
+
+    fn width(column: Column) -> Constraint {
+        match column {
+            Column::Path => Constraint::Percentage(35),
+            Column::Name => Constraint::Max(30),
+            Column::Size | Column::AllocatedSize => Constraint::Max(12),
+            Column::Age => Constraint::Max(14),
+            Column::TargetPath => Constraint::Percentage(25),
+            Column::GitBranch | Column::Toolchain => Constraint::Max(20),
+            Column::WorkspaceMembers => Constraint::Max(8),
+            Column::BuiltWith => Constraint::Max(24),
+        }
+    }
+}
+
+impl TableRow for TreeRow {
+    type Column = Column;
+
+    fn header(column: Column, size_unit: SizeUnit, sort: SortOrder) -> Cell<'static> {
+        ProjectTargetAnalysis::header(column, size_unit, sort)
+    }
+
+    fn cell(&self, column: Column, size_unit: SizeUnit) -> Cell<'_> {
+        let indent = "  ".repeat(self.depth());
+        match (self, column) {
+            (TreeRow::Project { label, .. }, Column::Path) => {
+                Cell::from(format!("{indent}{label}"))
+            }
+            (TreeRow::Project { project, .. }, column) => project.cell(column, size_unit),
+            (TreeRow::Directory(directory), column) => {
+                let text = match column {
+                    Column::Path => {
+                        let marker = if directory.collapsed { "▸" } else { "▾" };
+                        format!("{indent}{marker} {}/", directory.label)
+                    }
+                    Column::Name => format!("{} projects", directory.projects.len()),
+                    Column::Size => size_unit.format(directory.size),
+                    Column::AllocatedSize => size_unit.format(directory.allocated_size),
+                    Column::Age => {
+                        let age = SystemTime::now()
+                            .duration_since(directory.last_modified)
+                            .unwrap_or_default();
+                        format_age(age)
+                    }
+                    _ => String::new(),
+                };
+                Cell::from(text)
+            }
+        }
+    }
+
+    fn width(column: Column) -> Constraint {
+        ProjectTargetAnalysis::width(column)
+    }
+}
+
+/// Release of the compiler the target was built with, in red when it is no longer installed
+fn built_with_cell(project: &ProjectTargetAnalysis) -> Cell<'_> {
+    let release = project
+        .built_with
+        .as_deref()
+        .and_then(|it| it.split(' ').next())
+        .unwrap_or_default();
+    match project.compiler_installed {
+        Some(false) => {
+            Cell::from(format!("{release} (removed)")).style(Style::default().fg(Color::Red))
+        }
+        _ => Cell::from(release),
     }
 }
 
 pub enum DeleteState {
     Confirm,
-    Deleting(Arc<NotifyRwLock<Progress>>),
+    Deleting(Arc<NotifyRwLock<DeleteProgress>>),
+}
+
+/// Progress of a deletion started from the TUI
+pub struct DeleteProgress {
+    pub total: usize,
+    pub scanned: usize,
+    /// Entries that could not be cleaned or recorded in the journal
+    pub failed: usize,
+}
+
+impl DeleteProgress {
+    pub fn new(total: usize) -> Self {
+        DeleteProgress {
+            total,
+            scanned: 0,
+            failed: 0,
+        }
+    }
 }
 
 pub enum CursorMode {
@@ -56,11 +202,67 @@ pub struct App {
     pub items: Arc<NotifyRwLock<Vec<ProjectTargetAnalysis>>>,
     pub selected_items: HashSet<Uuid>,
     pub scan_progress: Arc<NotifyRwLock<Progress>>,
+    /// Set once every scanned project has been added to `items`, which happens after the scan
+    /// progress is complete
+    pub scan_finished: Arc<NotifyRwLock<bool>>,
     pub delete_state: Option<DeleteState>,
+    /// Projects of the running or finished deletion, the listed part of the selection
+    pub deleting_items: HashSet<Uuid>,
     pub dry_run: bool,
     pub mode: CursorMode,
     pub show_help_popup: bool,
     pub notify_tx: SyncSender<()>,
+    pub size_unit: SizeUnit,
+    pub clean_scope: CleanScope,
+    pub deletion_backend: DeletionBackend,
+    pub sweep_older_than: Duration,
+    /// Highlighted entry of the clean scope chooser while it is open
+    pub scope_chooser: Option<usize>,
+    /// Bytes the user wants to free, shown against the selection in the status bar
+    pub goal: Option<u64>,
+    /// Bytes the clean scope frees of each project chosen for `goal`
+    pub goal_sizes: HashMap<Uuid, u64>,
+    /// Goal given on the command line, applied once the scan has finished
+    pub pending_goal: Option<u64>,
+    /// Free space to keep given on the command line, applied once the scan has finished
+    pub pending_min_free: Option<u64>,
+    /// Text of the free-space goal prompt while it is open
+    pub goal_input: Option<String>,
+    pub disk_space: DiskSpace,
+    /// Paths of all discovered projects, including those without build output
+    pub known_projects: Arc<NotifyRwLock<Vec<PathBuf>>>,
+    /// Packages and toolchains the known projects need, read once the scan has finished
+    pub references: Option<ProjectReferences>,
+    pub cache_older_than: Duration,
+    /// Tabs for the caches in cargo's and rustup's home, shown after the project list
+    pub tabs: Vec<CacheTab>,
+    /// Shown tab, 0 is the project list and `n` is `tabs[n - 1]`
+    pub tab: usize,
+    /// Size per crate of the project opened from the project list
+    pub breakdown: Option<BreakdownView>,
+    /// Projects shown in the list, by default those with build output
+    pub filter: ProjectFilter,
+    /// Show every discovered project regardless of `filter`
+    pub show_all: bool,
+    /// Order of the project list, kept while projects are added
+    pub sort: SortOrder,
+    /// Row under the cursor, which the cursor follows when rows move
+    pub cursor_key: Option<RowKey>,
+    /// Columns of the project list in the order they are shown
+    pub columns: Vec<Column>,
+    /// Columns whose details are read for every project, shared with the thread adding the
+    /// scanned projects to `items`
+    pub filled_columns: Arc<NotifyRwLock<Vec<Column>>>,
+    /// Highlighted entry of the column chooser while it is open, an index into `Column::ALL`
+    pub column_chooser: Option<usize>,
+    /// Query narrowing the project list down, typed after `/`
+    pub search: Option<SearchQuery>,
+    /// Text of the search prompt while it is open
+    pub search_input: Option<String>,
+    /// List the projects grouped by directory instead of flat
+    pub tree_view: bool,
+    /// Directories of the tree view whose rows are hidden
+    pub collapsed: HashSet<PathBuf>,
 }
 
 impl App {
@@ -74,18 +276,427 @@ impl App {
             items: Arc::new(NotifyRwLock::new(notify_tx.clone(), vec![])),
             selected_items: HashSet::new(),
             scan_progress,
+            scan_finished: Arc::new(NotifyRwLock::new(notify_tx.clone(), false)),
             delete_state: None,
+            deleting_items: HashSet::new(),
             mode: CursorMode::Normal,
             show_help_popup: false,
             dry_run,
-            notify_tx,
+            notify_tx: notify_tx.clone(),
+            size_unit: SizeUnit::default(),
+            clean_scope: CleanScope::default(),
+            deletion_backend: DeletionBackend::default(),
+            sweep_older_than: Config::default().sweep_older_than.0,
+            scope_chooser: None,
+            goal: None,
+            goal_sizes: HashMap::new(),
+            pending_goal: None,
+            pending_min_free: None,
+            goal_input: None,
+            disk_space: DiskSpace::default(),
+            known_projects: Arc::new(NotifyRwLock::new(notify_tx.clone(), vec![])),
+            references: None,
+            cache_older_than: Config::default().cache_older_than.0,
+            tabs: vec![],
+            tab: 0,
+            breakdown: None,
+            filter: ProjectFilter::default(),
+            show_all: false,
+            sort: SortOrder::default(),
+            cursor_key: None,
+            columns: Column::DEFAULT.to_vec(),
+            filled_columns: Arc::new(NotifyRwLock::new(notify_tx.clone(), vec![])),
+            column_chooser: None,
+            search: None,
+            search_input: None,
+            tree_view: false,
+            collapsed: HashSet::new(),
+        }
+    }
+
+    /// Take over the display and cleanup preferences of the effective configuration
+    pub fn apply_config(&mut self, config: &Config) {
+        self.size_unit = config.size_unit;
+        self.clean_scope = config.clean_scope;
+        self.deletion_backend = config.deletion_backend;
+        self.cache_older_than = config.cache_older_than.0;
+        self.sweep_older_than = config.sweep_older_than.0;
+        self.sort = config.sort;
+        self.columns = config.columns.clone();
+        let sort_column = Column::ALL
+            .into_iter()
+            .find(|it| it.sort_key() == config.sort.key);
+        for column in config.columns.iter().copied().chain(sort_column) {
+            self.fill_column(column);
+        }
+    }
+
+    /// Read the details `column` shows for the projects found so far and those still to come
+    fn fill_column(&mut self, column: Column) {
+        if self.filled_columns.read().contains(&column) {
+            return;
+        }
+        // The scanning thread checks the columns again while holding `items`, so registering the
+        // column first leaves no project without it
+        self.filled_columns.write().push(column);
+        for project in self.items.write().iter_mut() {
+            project.fill_columns(&[column]);
+        }
+    }
+
+    pub fn clean_options(&self) -> CleanOptions {
+        CleanOptions {
+            scope: self.clean_scope,
+            backend: self.deletion_backend,
+            sweep_older_than: self.sweep_older_than,
+        }
+    }
+
+    /// Add a tab for the `kind` cache in `home`
+    pub fn add_cache_tab(&mut self, kind: CacheKind, home: PathBuf) {
+        let mut tab = CacheTab::new(kind, home, self.notify_tx.clone());
+        tab.older_than = self.cache_older_than;
+        self.tabs.push(tab);
+    }
+
+    /// Whether `project` is listed, hidden projects are only listed with `show_all`
+    pub fn shows(&self, project: &ProjectTargetAnalysis) -> bool {
+        let now = SystemTime::now();
+        (self.show_all || self.filter.matches(project, now)) && self.matches_search(project, now)
+    }
+
+    fn matches_search(&self, project: &ProjectTargetAnalysis, now: SystemTime) -> bool {
+        self.search
+            .as_ref()
+            .is_none_or(|search| search.matches(project, now))
+    }
+
+    /// The listed projects in the order of the table
+    pub fn shown_items(&self) -> Vec<ProjectTargetAnalysis> {
+        let items = self.items.read();
+        items.iter().filter(|it| self.shows(it)).cloned().collect()
+    }
+
+    /// The selected projects among the listed ones, hidden projects are never cleaned
+    pub fn selected_shown_items(&self) -> Vec<ProjectTargetAnalysis> {
+        let mut items = self.shown_items();
+        items.retain(|it| self.selected_items.contains(&it.id));
+        items
+    }
+
+    /// The rows of the table, a row per listed project or the directory tree of them
+    pub fn rows(&self) -> Vec<TreeRow> {
+        match self.tree_view {
+            true => tree_rows(&self.shown_items(), &self.collapsed),
+            false => flat_rows(self.shown_items()),
+        }
+    }
+
+    fn row_at_cursor(&self) -> Option<TreeRow> {
+        let selected = self.table_state.selected()?;
+        self.rows().into_iter().nth(selected)
+    }
+
+    /// Remember the row under the cursor
+    fn remember_cursor(&mut self) {
+        if let Some(row) = self.row_at_cursor() {
+            self.cursor_key = Some(row.key());
+        }
+    }
+
+    /// Move the cursor back onto the remembered row after rows were added, moved or hidden
+    fn restore_cursor(&mut self) {
+        let rows = self.rows();
+        let index = self
+            .cursor_key
+            .as_ref()
+            .and_then(|key| rows.iter().position(|it| it.key() == *key))
+            .or_else(|| self.table_state.selected())
+            .map(|it| it.min(rows.len().saturating_sub(1)));
+        self.table_state.select(index);
+    }
+
+    /// Order the projects by `sort`, keeping the cursor on its project
+    pub fn sort_items(&mut self) {
+        let sorted = {
+            let items = self.items.read();
+            items.is_sorted_by(|a, b| self.sort.compare(a, b).is_le())
+        };
+        if !sorted {
+            self.items.write().sort_by(|a, b| self.sort.compare(a, b));
+        }
+        self.restore_cursor();
+    }
+
+    /// Switch between the filtered and the complete project list, staying on the same project
+    fn toggle_show_all(&mut self) {
+        self.remember_cursor();
+        self.show_all = !self.show_all;
+        self.restore_cursor();
+    }
+
+    /// Switch between the flat list and the directory tree, staying on the same row
+    fn toggle_tree_view(&mut self) {
+        self.remember_cursor();
+        self.tree_view = !self.tree_view;
+        self.restore_cursor();
+    }
+
+    /// Collapse or expand the directory under the cursor, `None` toggles it
+    fn collapse(&mut self, collapse: Option<bool>) {
+        let Some(TreeRow::Directory(directory)) = self.row_at_cursor() else {
+            return;
+        };
+        match collapse.unwrap_or(!directory.collapsed) {
+            true => self.collapsed.insert(directory.path),
+            false => self.collapsed.remove(&directory.path),
+        };
+    }
+
+    /// Move the cursor to the directory row the row under the cursor is listed under
+    fn move_to_parent(&mut self) {
+        let Some(selected) = self.table_state.selected() else {
+            return;
+        };
+        let rows = self.rows();
+        let Some(depth) = rows.get(selected).map(|it| it.depth()) else {
+            return;
+        };
+        if let Some(parent) = rows[..selected].iter().rposition(|it| it.depth() < depth) {
+            self.table_state.select(Some(parent));
+        }
+    }
+
+    /// Move the cursor to the next (or previous) listed project matching the search, wrapping
+    /// around at the end of the list
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.search.is_none() {
+            return;
+        }
+        let now = SystemTime::now();
+        let rows = self.rows();
+        let current = self.table_state.selected().unwrap_or(0);
+        let len = rows.len();
+        let next = (1..=len)
+            .map(|offset| match forward {
+                true => (current + offset) % len,
+                false => (current + len - offset % len) % len,
+            })
+            .find(|it| {
+                rows[*it]
+                    .project()
+                    .is_some_and(|project| self.matches_search(project, now))
+            });
+        if let Some(next) = next {
+            self.table_state.select(Some(next));
+        }
+    }
+
+    /// Select every listed project, or unselect them when all of them are selected already
+    fn toggle_select_shown(&mut self) {
+        let shown = self.shown_items();
+        if shown.iter().all(|it| self.selected_items.contains(&it.id)) {
+            for project in &shown {
+                self.selected_items.remove(&project.id);
+            }
+        } else {
+            self.selected_items.extend(shown.iter().map(|it| it.id));
+        }
+    }
+
+    /// Replace the selection with the stalest projects that together free at least `goal` bytes
+    pub fn apply_goal(&mut self, goal: u64) {
+        let options = self.clean_options();
+        let plan = plan_free_space(self.shown_items().iter(), goal, SystemTime::now(), |it| {
+            reclaimable_size(it, &options)
+        });
+        self.selected_items = plan.selected.into_iter().collect();
+        self.goal_sizes = plan.sizes;
+        self.goal = Some(goal);
+    }
+
+    /// Replace the selection with the stalest projects needed to leave at least `min_free` bytes
+    /// available on every filesystem holding projects
+    pub fn apply_min_free(&mut self, min_free: u64) {
+        let options = self.clean_options();
+        let plan = plan_min_free(
+            &self.shown_items(),
+            min_free,
+            &mut self.disk_space,
+            SystemTime::now(),
+            |it| reclaimable_size(it, &options),
+        );
+        self.selected_items = plan.selected.into_iter().collect();
+        self.goal_sizes = plan.sizes;
+        self.goal = Some(plan.goal);
+    }
+
+    /// Called whenever a background job changed shared state
+    pub fn on_async_update(&mut self) {
+        self.sort_items();
+        let scan_finished = *self.scan_finished.read();
+        if scan_finished {
+            if let Some(goal) = self.pending_goal.take() {
+                self.apply_goal(goal);
+            }
+            if let Some(min_free) = self.pending_min_free.take() {
+                self.apply_min_free(min_free);
+            }
+            self.mark_cache_usage();
+        }
+    }
+
+    /// Compare the analyzed caches against the lockfiles of the scanned projects
+    fn mark_cache_usage(&mut self) {
+        let pending = self
+            .tabs
+            .iter()
+            .any(|it| !it.usage_marked && it.is_scanned());
+        if !pending {
+            return;
+        }
+        let references = self.references.get_or_insert_with(|| {
+            let projects = self.known_projects.read();
+            ProjectReferences::collect(projects.iter().map(PathBuf::as_path)).0
+        });
+        for tab in self
+            .tabs
+            .iter_mut()
+            .filter(|it| !it.usage_marked && it.is_scanned())
+        {
+            tab.usage_marked = true;
+            tab.kind.mark_in_use(&mut tab.items.write(), references);
+        }
+    }
+
+    fn handle_goal_input(&mut self, key: KeyCode) {
+        let Some(input) = &mut self.goal_input else {
+            return;
+        };
+        match key {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                // Invalid input keeps the prompt open, the prompt shows why
+                if let Ok(goal) = parse_size(input) {
+                    self.goal_input = None;
+                    self.apply_goal(goal);
+                }
+            }
+            KeyCode::Esc => self.goal_input = None,
+            _ => {}
+        }
+    }
+
+    fn handle_search_input(&mut self, key: KeyCode) {
+        let Some(input) = &mut self.search_input else {
+            return;
+        };
+        match key {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            // Invalid input keeps the prompt open, the prompt shows why
+            KeyCode::Enter if input.parse::<SearchQuery>().is_err() => return,
+            KeyCode::Enter => {
+                self.search_input = None;
+                return;
+            }
+            KeyCode::Esc => {
+                self.search_input = None;
+                self.search = None;
+                self.restore_cursor();
+                return;
+            }
+            _ => return,
+        }
+        // The list follows the query while it is typed, incomplete conditions leave it as it is
+        if let Ok(search) = input.parse::<SearchQuery>() {
+            self.search = Some(search).filter(|it| !it.is_empty());
+            self.restore_cursor();
+        }
+    }
+
+    fn handle_scope_chooser(&mut self, key: KeyCode) {
+        let Some(index) = &mut self.scope_chooser else {
+            return;
+        };
+        let scopes = CleanScope::value_variants();
+        match key {
+            KeyCode::Char('j') | KeyCode::Down => *index = (*index + 1).min(scopes.len() - 1),
+            KeyCode::Char('k') | KeyCode::Up => *index = index.saturating_sub(1),
+            KeyCode::Enter => {
+                if self.clean_scope != scopes[*index] {
+                    // The goal was planned with what the previous scope frees
+                    self.goal = None;
+                    self.goal_sizes.clear();
+                }
+                self.clean_scope = scopes[*index];
+                self.scope_chooser = None;
+            }
+            KeyCode::Esc | KeyCode::Char(SCOPE_COMMAND_KEY) => self.scope_chooser = None,
+            _ => {}
+        }
+    }
+
+    fn handle_column_chooser(&mut self, key: KeyCode) {
+        let Some(index) = &mut self.column_chooser else {
+            return;
+        };
+        match key {
+            KeyCode::Char('j') | KeyCode::Down => *index = (*index + 1).min(Column::ALL.len() - 1),
+            KeyCode::Char('k') | KeyCode::Up => *index = index.saturating_sub(1),
+            KeyCode::Char(' ') => {
+                let column = Column::ALL[*index];
+                match self.columns.iter().position(|it| *it == column) {
+                    // The table keeps at least one column
+                    Some(_) if self.columns.len() == 1 => {}
+                    Some(position) => {
+                        self.columns.remove(position);
+                    }
+                    None => {
+                        self.columns.push(column);
+                        self.fill_column(column);
+                    }
+                }
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char(COLUMNS_COMMAND_KEY) => {
+                self.column_chooser = None
+            }
+            _ => {}
+        }
+    }
+
+    /// Columns of the table, the tree view always starts with the tree of paths
+    pub fn table_columns(&self) -> Vec<Column> {
+        match self.tree_view {
+            true => std::iter::once(Column::Path)
+                .chain(
+                    self.columns
+                        .iter()
+                        .copied()
+                        .filter(|it| *it != Column::Path),
+                )
+                .collect(),
+            false => self.columns.clone(),
         }
     }
 
+    /// Sort keys of the shown columns, which `s` cycles through
+    fn column_sort_keys(&self) -> Vec<SortKey> {
+        self.table_columns()
+            .iter()
+            .map(|it| it.sort_key())
+            .collect()
+    }
+
     pub fn next(&mut self) {
         let i = match self.table_state.selected() {
             Some(i) => {
-                if i >= self.items.read().len() - 1 {
+                if i + 1 >= self.rows().len() {
                     i
                 } else {
                     i + 1
@@ -110,7 +721,80 @@ impl App {
         self.table_state.select(Some(i));
     }
 
+    /// Show the tab `offset` tabs after (or before) the current one
+    fn switch_tab(&mut self, offset: isize) {
+        let count = self.tabs.len() as isize + 1;
+        self.tab = (self.tab as isize + offset).rem_euclid(count) as usize;
+        if let Some(tab) = self.tab.checked_sub(1).map(|it| &mut self.tabs[it]) {
+            tab.start_scan();
+        }
+    }
+
     pub fn handle_key(&mut self, key: KeyCode) -> Option<()> {
+        let result = self.dispatch_key(key);
+        if self.tab == 0 && self.breakdown.is_none() {
+            self.remember_cursor();
+        }
+        result
+    }
+
+    fn dispatch_key(&mut self, key: KeyCode) -> Option<()> {
+        if self.goal_input.is_some() {
+            self.handle_goal_input(key);
+            return Some(());
+        }
+        if self.search_input.is_some() {
+            self.handle_search_input(key);
+            return Some(());
+        }
+        if self.scope_chooser.is_some() {
+            self.handle_scope_chooser(key);
+            return Some(());
+        }
+        if self.column_chooser.is_some() {
+            self.handle_column_chooser(key);
+            return Some(());
+        }
+        if let Some(view) = &mut self.breakdown {
+            match key {
+                KeyCode::Char('q') => return None,
+                KeyCode::Char('h') => self.show_help_popup = !self.show_help_popup,
+                key => {
+                    self.show_help_popup = false;
+                    if !view.handle_key(key) {
+                        self.breakdown = None;
+                    }
+                }
+            }
+            return Some(());
+        }
+        let deleting = match self.tab.checked_sub(1) {
+            Some(index) => self.tabs[index].delete_state.is_some(),
+            None => self.delete_state.is_some(),
+        };
+        match key {
+            KeyCode::Tab if !deleting => {
+                self.switch_tab(1);
+                return Some(());
+            }
+            KeyCode::BackTab if !deleting => {
+                self.switch_tab(-1);
+                return Some(());
+            }
+            _ => {}
+        }
+        if let Some(index) = self.tab.checked_sub(1) {
+            match key {
+                KeyCode::Char('q') => return None,
+                KeyCode::Char('h') => self.show_help_popup = !self.show_help_popup,
+                KeyCode::Esc => {
+                    self.show_help_popup = false;
+                    self.tabs[index].handle_key(key, self.dry_run);
+                }
+                _ => self.tabs[index].handle_key(key, self.dry_run),
+            }
+            return Some(());
+        }
         match key {
             KeyCode::Char('q') => return None,
             KeyCode::Char(DELETE_COMMAND_KEY) => {
@@ -120,15 +804,23 @@ impl App {
                     Some(DeleteState::Deleting(delete_progress)) => {
                         let progress = delete_progress.read();
                         if progress.scanned == progress.total {
-                            self.items
-                                .write()
-                                .retain(|it| !self.selected_items.contains(&it.id));
-                            self.selected_items.clear();
+                            // Cleaned projects keep their remaining size, in a dry-run they
+                            // disappear as if they were cleaned
+                            if self.dry_run {
+                                self.items
+                                    .write()
+                                    .retain(|it| !self.deleting_items.contains(&it.id));
+                            }
+                            // Selected projects hidden by the search stay selected
+                            self.selected_items
+                                .retain(|it| !self.deleting_items.contains(it));
+                            self.deleting_items.clear();
+                            self.disk_space.invalidate();
                             is_reset = true;
                         }
                     }
                     None => {
-                        if !self.selected_items.is_empty() {
+                        if !self.selected_shown_items().is_empty() {
                             self.delete_state = Some(DeleteState::Confirm);
                         }
                     }
@@ -139,48 +831,57 @@ impl App {
             }
             KeyCode::Char('Y') => {
                 if let Some(DeleteState::Confirm) = self.delete_state {
-                    let items = self.items.read();
-                    let selected_items = self.selected_items.clone();
-                    let remove_targets = items
-                        .iter()
-                        .filter(|it| selected_items.contains(&it.id))
-                        .cloned()
-                        .collect_vec();
-
-                    assert_eq!(remove_targets.len(), selected_items.len());
+                    // Hidden projects are left alone
+                    let remove_targets = self.selected_shown_items();
+                    self.deleting_items = remove_targets.iter().map(|it| it.id).collect();
 
                     let delete_progress = Arc::new(NotifyRwLock::new(
                         self.notify_tx.clone(),
-                        Progress {
-                            total: remove_targets.len(),
-                            scanned: 0,
-                        },
+                        DeleteProgress::new(remove_targets.len()),
                     ));
                     self.delete_state = Some(DeleteState::Deleting(delete_progress.clone()));
                     let dry_run = self.dry_run;
+                    let options = self.clean_options();
+                    let items = Arc::clone(&self.items);
                     std::thread::spawn(move || {
                         for target in remove_targets {
                             if dry_run {
                                 std::thread::sleep(std::time::Duration::from_millis(1000));
                             } else {
-                                std::process::Command::new("cargo")
-                                    .arg("clean")
-                                    .current_dir(target.project_path.clone())
-                                    .stderr(std::process::Stdio::null())
-                                    .spawn()
-                                    .expect("failed to execute process")
-                                    .wait()
-                                    .expect("failed to wait for process");
+                                // A failing project must not stop the remaining ones
+                                let result = clean_and_measure(&target, &options);
+                                let journaled = journal::append(&JournalEntry::new(
+                                    "tui",
+                                    &target.project_path,
+                                    options.scope.as_str(),
+                                    &result,
+                                ));
+                                if result.is_err() || journaled.is_err() {
+                                    delete_progress.write().failed += 1;
+                                }
+                                if let Ok(freed) = result {
+                                    let mut items = items.write();
+                                    if let Some(it) = items.iter_mut().find(|it| it.id == target.id)
+                                    {
+                                        it.size = it.size.saturating_sub(freed);
+                                    }
+                                }
                             }
                             delete_progress.write().scanned += 1;
                         }
                     });
                 }
             }
+            KeyCode::Char('n') if matches!(self.delete_state, Some(DeleteState::Confirm)) => {
+                self.delete_state = None;
+            }
             KeyCode::Char('n') => {
-                if let Some(DeleteState::Confirm) = self.delete_state {
-                    self.delete_state = None;
-                }
+                self.jump_to_match(true);
+                after_move(self);
+            }
+            KeyCode::Char('N') => {
+                self.jump_to_match(false);
+                after_move(self);
             }
             KeyCode::Char('j') | KeyCode::Down => {
                 self.next();
@@ -195,19 +896,20 @@ impl App {
                 after_move(self);
             }
             KeyCode::Char('G') => {
-                {
-                    let items = self.items.read();
-                    self.table_state.select(Some(items.len() - 1));
-                }
+                let len = self.rows().len();
+                self.table_state.select(Some(len.saturating_sub(1)));
                 after_move(self);
             }
             KeyCode::Char(' ') => {
-                if let Some(selected) = self.table_state.selected() {
-                    let selected_id = self.items.read()[selected].id;
-                    if self.selected_items.contains(&selected_id) {
-                        self.selected_items.remove(&selected_id);
+                // A directory of the tree selects or unselects all projects below it
+                if let Some(row) = self.row_at_cursor() {
+                    let ids = row.project_ids();
+                    if ids.iter().all(|it| self.selected_items.contains(it)) {
+                        for id in &ids {
+                            self.selected_items.remove(id);
+                        }
                     } else {
-                        self.selected_items.insert(selected_id);
+                        self.selected_items.extend(ids);
                     }
                 }
             }
@@ -219,6 +921,55 @@ impl App {
                 self.mode = CursorMode::Unselect;
                 after_move(self);
             }
+            KeyCode::Char(GOAL_COMMAND_KEY) if self.delete_state.is_none() => {
+                self.goal_input = Some(String::new());
+            }
+            KeyCode::Char(SCOPE_COMMAND_KEY) if self.delete_state.is_none() => {
+                let current = CleanScope::value_variants()
+                    .iter()
+                    .position(|it| *it == self.clean_scope);
+                self.scope_chooser = Some(current.unwrap_or_default());
+            }
+            KeyCode::Char(SHOW_ALL_COMMAND_KEY) if self.delete_state.is_none() => {
+                self.toggle_show_all();
+            }
+            KeyCode::Char(SEARCH_COMMAND_KEY) if self.delete_state.is_none() => {
+                let query = self.search.as_ref().map(|it| it.to_string());
+                self.search_input = Some(query.unwrap_or_default());
+            }
+            KeyCode::Char(SELECT_ALL_COMMAND_KEY) if self.delete_state.is_none() => {
+                self.toggle_select_shown();
+            }
+            KeyCode::Char(TREE_COMMAND_KEY) => {
+                self.toggle_tree_view();
+            }
+            KeyCode::Left => match self.row_at_cursor() {
+                Some(TreeRow::Directory(directory)) if !directory.collapsed => {
+                    self.collapse(Some(true))
+                }
+                _ => self.move_to_parent(),
+            },
+            KeyCode::Right => self.collapse(Some(false)),
+            KeyCode::Char(COLUMNS_COMMAND_KEY) if self.delete_state.is_none() => {
+                self.column_chooser = Some(0);
+            }
+            KeyCode::Char(SORT_COMMAND_KEY) => {
+                self.remember_cursor();
+                self.sort = self.sort.next_key(&self.column_sort_keys());
+                self.sort_items();
+            }
+            KeyCode::Char(REVERSE_SORT_COMMAND_KEY) => {
+                self.remember_cursor();
+                self.sort = self.sort.reversed();
+                self.sort_items();
+            }
+            BREAKDOWN_COMMAND_KEY if self.delete_state.is_none() => match self.row_at_cursor() {
+                Some(TreeRow::Directory(_)) => self.collapse(None),
+                Some(TreeRow::Project { project, .. }) => {
+                    self.breakdown = Some(BreakdownView::open(&project, self.notify_tx.clone()));
+                }
+                None => {}
+            },
             KeyCode::Char('h') => {
                 self.show_help_popup = !self.show_help_popup;
             }
@@ -233,64 +984,114 @@ impl App {
 }
 
 pub fn after_move(app: &mut App) {
+    // Moving over a directory of the tree leaves its projects as they are
+    let Some(selected_id) = app
+        .row_at_cursor()
+        .and_then(|row| row.project().map(|it| it.id))
+    else {
+        return;
+    };
     match app.mode {
         CursorMode::Normal => {}
         CursorMode::Select => {
-            if let Some(selected) = app.table_state.selected() {
-                let selected_id = app.items.read()[selected].id;
-                app.selected_items.insert(selected_id);
-            }
+            app.selected_items.insert(selected_id);
         }
         CursorMode::Unselect => {
-            if let Some(selected) = app.table_state.selected() {
-                let selected_id = app.items.read()[selected].id;
-                app.selected_items.remove(&selected_id);
-            }
+            app.selected_items.remove(&selected_id);
         }
     }
 }
 
 pub fn ui(f: &mut Frame, app: &mut App) {
+    let file_systems = {
+        let items = app.shown_items();
+        app.disk_space.summarize(&items, &app.selected_items)
+    };
+    // Projects spread over several mounts get one extra status line per filesystem
+    let status_height = match file_systems.len() {
+        0 | 1 => 1,
+        n => 1 + n as u16,
+    };
+
+    let tabs_height = if app.tabs.is_empty() { 0 } else { 1 };
     let height = f.area().height;
     let rects = Layout::default()
         .direction(Direction::Vertical)
         .spacing(0)
         .constraints([
             Constraint::Max(1),
-            Constraint::Max(height - 2),
-            Constraint::Max(1),
+            Constraint::Max(tabs_height),
+            Constraint::Max(height.saturating_sub(1 + tabs_height + status_height)),
+            Constraint::Max(status_height),
         ])
         .split(f.area());
 
-    {
+    if !app.tabs.is_empty() {
+        let titles = std::iter::once("Projects").chain(app.tabs.iter().map(|it| it.kind.title()));
+        let tabs = Tabs::new(titles)
+            .select(app.tab)
+            .style(Style::default().fg(Color::Green))
+            .highlight_style(Style::default().fg(Color::White).bg(Color::Blue));
+        f.render_widget(tabs, rects[1]);
+    }
+
+    if let Some(index) = app.tab.checked_sub(1) {
+        cache_tab::render(
+            f,
+            &mut app.tabs[index],
+            [rects[0], rects[2], rects[3]],
+            app.size_unit,
+            app.dry_run,
+        );
+        help_popup(f, app);
+        return;
+    }
+
+    if let Some(view) = &mut app.breakdown {
+        breakdown::render(f, view, [rects[2], rects[3]], app.size_unit);
+    } else {
         let selected_style = Style::default().fg(Color::White).bg(Color::Green);
-        let header = Row::new(ProjectTargetAnalysis::header());
-        let items = app.items.read();
+        let columns = app.table_columns();
+        let header = Row::new(
+            columns
+                .iter()
+                .map(|it| TreeRow::header(*it, app.size_unit, app.sort)),
+        );
+        let items = app.rows();
         let rows = items.iter().map(|item| {
-            let cells = item.cells();
+            let cells = columns.iter().map(|it| item.cell(*it, app.size_unit));
             let row = Row::new(cells).height(1).bottom_margin(0);
-            if app.selected_items.contains(&item.id) {
-                row.style(Style::default().fg(Color::Blue).bg(Color::Yellow))
-            } else {
-                row.style(Style::default().fg(Color::Green))
+            let ids = item.project_ids();
+            let selected = ids
+                .iter()
+                .filter(|it| app.selected_items.contains(it))
+                .count();
+            match item {
+                _ if selected == ids.len() => {
+                    row.style(Style::default().fg(Color::Blue).bg(Color::Yellow))
+                }
+                // Directories with some of their projects selected
+                TreeRow::Directory(_) if selected > 0 => {
+                    row.style(Style::default().fg(Color::Yellow))
+                }
+                TreeRow::Directory(_) => row.style(Style::default().fg(Color::Cyan)),
+                // Never built, or cleaned completely
+                TreeRow::Project { project, .. } if project.size == 0 && !project.orphaned => {
+                    row.style(Style::default().fg(Color::DarkGray))
+                }
+                TreeRow::Project { .. } => row.style(Style::default().fg(Color::Green)),
             }
         });
-        let t = Table::new(
-            rows,
-            &[
-                Constraint::Percentage(50),
-                Constraint::Max(30),
-                Constraint::Max(10),
-            ],
-        )
-        .header(header)
-        .block(Block::default().borders(Borders::ALL).title(format!(
-            "Cargo Cleaner {}",
-            if app.dry_run { "(dry-run)" } else { "" }
-        )))
-        .row_highlight_style(selected_style)
-        .highlight_symbol(">> ");
-        f.render_stateful_widget(t, rects[1], &mut app.table_state);
+        let widths = columns.iter().map(|it| TreeRow::width(*it));
+        let t = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Cargo Cleaner {}",
+                if app.dry_run { "(dry-run)" } else { "" }
+            )))
+            .row_highlight_style(selected_style)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(t, rects[2], &mut app.table_state);
     }
 
     {
@@ -307,8 +1108,19 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         f.render_widget(gauge, rects[0]);
     }
 
-    status_bar(f, app, rects[2]);
+    if app.breakdown.is_none() {
+        status_bar(f, app, rects[3], &file_systems);
+    }
 
+    help_popup(f, app);
+    goal_popup(f, app);
+    search_popup(f, app);
+    scope_popup(f, app);
+    columns_popup(f, app);
+    delete_popup(f, app);
+}
+
+pub fn help_popup(f: &mut Frame, app: &mut App) {
     if app.show_help_popup {
         let text = Text::styled(
             "h      : toggle help\n\
@@ -319,7 +1131,20 @@ pub fn ui(f: &mut Frame, app: &mut App) {
              space  : toggle select\n\
              v      : into select mode\n\
              V      : into unselect mode\n\
+             f      : select stalest projects to free a size\n\
              d      : open delete window\n\
+             enter  : show the size per crate, s sorts\n\
+             c      : choose what is cleaned or removed\n\
+             a      : show all projects, also those never built\n\
+             s or S : sort by the next column, reverse the order\n\
+             C      : choose the columns\n\
+             /      : search, e.g. client size>1G age>30d\n\
+             n or N : jump to the next or previous match\n\
+             A      : select or unselect all listed projects\n\
+             t      : switch between the directory tree and the list\n\
+             ← or → : collapse or expand the directory, enter toggles\n\
+             u      : select unused cache entries\n\
+             tab    : switch between projects and caches\n\
              q      : quit",
             Style::default().fg(Color::Yellow),
         );
@@ -335,14 +1160,131 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         let paragraph = Paragraph::new(text).block(block);
         f.render_widget(paragraph, area);
     }
+}
 
-    delete_popup(f, app);
+pub fn goal_popup(f: &mut Frame, app: &mut App) {
+    let Some(input) = &app.goal_input else {
+        return;
+    };
+    let hint = match parse_size(input) {
+        Ok(goal) => format!("= {}", app.size_unit.format(goal)),
+        Err(_) if input.is_empty() => "e.g. 50GiB".to_string(),
+        Err(_) => "invalid size".to_string(),
+    };
+    let text = Text::from(vec![
+        Line::styled(format!("> {input}"), Style::default().fg(Color::Yellow)),
+        Line::styled(hint, Style::default().fg(Color::Gray)),
+    ]);
+
+    let area = sized_centered_rect(40.min(f.area().width), text.height() as u16 + 2, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title("Free how much? (Enter/Esc)")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+pub fn search_popup(f: &mut Frame, app: &mut App) {
+    let Some(input) = &app.search_input else {
+        return;
+    };
+    let hint = match input.parse::<SearchQuery>() {
+        Err(err) => format!("invalid: {err}"),
+        Ok(_) if input.trim().is_empty() => "e.g. client size>1G age>30d".to_string(),
+        Ok(_) => format!("matches: {}", app.shown_items().len()),
+    };
+    let text = Text::from(vec![
+        Line::styled(format!("/{input}"), Style::default().fg(Color::Yellow)),
+        Line::styled(hint, Style::default().fg(Color::Gray)),
+    ]);
+
+    let area = sized_centered_rect(60.min(f.area().width), text.height() as u16 + 2, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title("Search (Enter/Esc)")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+pub fn scope_popup(f: &mut Frame, app: &mut App) {
+    let Some(index) = app.scope_chooser else {
+        return;
+    };
+    let items = CleanScope::value_variants()
+        .iter()
+        .map(|scope| {
+            let help = scope
+                .to_possible_value()
+                .and_then(|it| it.get_help().map(|it| it.to_string()))
+                .unwrap_or_default();
+            let line = format!("{:<17}{help}", scope.as_str());
+            if *scope == app.clean_scope {
+                ListItem::new(Line::styled(line, Style::default().fg(Color::Yellow)))
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect_vec();
+    let height = items.len() as u16 + 2;
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Clean scope (Enter/Esc)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().fg(Color::White).bg(Color::Green));
+
+    let area = sized_centered_rect(100.min(f.area().width), height, f.area());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        list,
+        area,
+        &mut ListState::default().with_selected(Some(index)),
+    );
+}
+
+pub fn columns_popup(f: &mut Frame, app: &mut App) {
+    let Some(index) = app.column_chooser else {
+        return;
+    };
+    let items = Column::ALL
+        .iter()
+        .map(|column| {
+            let mark = if app.columns.contains(column) {
+                "x"
+            } else {
+                " "
+            };
+            ListItem::new(format!("[{mark}] {}", column.as_str()))
+        })
+        .collect_vec();
+    let height = items.len() as u16 + 2;
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Columns (space/Enter)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().fg(Color::White).bg(Color::Green));
+
+    let area = sized_centered_rect(40.min(f.area().width), height, f.area());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        list,
+        area,
+        &mut ListState::default().with_selected(Some(index)),
+    );
 }
 
 pub fn delete_popup(f: &mut Frame, app: &mut App) {
     if let Some(delete_state) = &app.delete_state {
         let size = f.area();
-        let block = Block::default().borders(Borders::ALL);
+        let selected = app.selected_shown_items().len();
+        let hidden = app.selected_items.len() - selected;
+        let mut block = Block::default().borders(Borders::ALL);
+        if hidden > 0 && matches!(delete_state, DeleteState::Confirm) {
+            block = block.title(format!(" {hidden} selected but hidden crates are kept "));
+        }
         let area = centered_rect(60, 30, size);
         f.render_widget(Clear, area);
         let gauge = Gauge::default()
@@ -350,18 +1292,38 @@ pub fn delete_popup(f: &mut Frame, app: &mut App) {
             .gauge_style(Style::new().light_blue().on_black())
             .red();
 
+        let prompt = match app.clean_scope {
+            CleanScope::All => format!(
+                "Are you sure you want to delete the target directory for {} crates? (Y/n)",
+                selected
+            ),
+            CleanScope::Sweep => format!(
+                "Are you sure you want to delete the artifacts unused for {} of {} crates? (Y/n)",
+                HumanDuration(app.sweep_older_than),
+                selected
+            ),
+            CleanScope::Check => format!(
+                "Are you sure you want to delete the rust-analyzer and check artifacts of {} crates? (Y/n)",
+                selected
+            ),
+            CleanScope::DedupeDeps => format!(
+                "Are you sure you want to delete the superseded dependency builds of {} crates? (Y/n)",
+                selected
+            ),
+            scope => format!(
+                "Are you sure you want to delete the {} artifacts for {} crates? (Y/n)",
+                scope.as_str(),
+                selected
+            ),
+        };
         let gauge = match delete_state {
-            DeleteState::Confirm => gauge.percent(0).label(Span::styled(
-                format!(
-                    "Are you sure you want to delete the target directory for {} crates? (Y/n)",
-                    app.selected_items.len()
-                ),
-                Style::default().fg(Color::Yellow),
-            )),
+            DeleteState::Confirm => gauge
+                .percent(0)
+                .label(Span::styled(prompt, Style::default().fg(Color::Yellow))),
             DeleteState::Deleting(progress) => {
                 let progress = progress.read();
                 gauge
-                    .percent(progress_percent(&progress))
+                    .percent(percent(progress.scanned, progress.total))
                     .label(Span::styled(
                         delete_progress_text(&progress, app.dry_run),
                         Style::default().fg(Color::Yellow),
@@ -373,34 +1335,125 @@ pub fn delete_popup(f: &mut Frame, app: &mut App) {
     }
 }
 
-pub fn status_bar(f: &mut Frame, app: &mut App, rect: Rect) {
+pub fn status_bar(f: &mut Frame, app: &mut App, rect: Rect, file_systems: &[FileSystemSummary]) {
+    let lines = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(rect);
     let rects = Layout::default()
         .direction(Direction::Horizontal)
         .spacing(0)
         .constraints([
             Constraint::Min(50),
-            Constraint::Min(20),
-            Constraint::Min(10),
+            Constraint::Length(9),
+            Constraint::Length(10),
         ])
-        .split(rect);
-    let items = app.items.read();
-    let total_gib_size = items.iter().map(|it| it.size).sum::<u64>() as f64 / (GIB_SIZE as f64);
-    let selected_gib_size = items
+        .split(lines[0]);
+    let items = app.shown_items();
+    let total_size = items.iter().map(|it| it.size).sum::<u64>();
+    // Projects hidden after they were selected are not cleaned
+    let selected_size = items
         .iter()
         .filter(|it| app.selected_items.contains(&it.id))
         .map(|it| it.size)
-        .sum::<u64>() as f64
-        / (GIB_SIZE as f64);
+        .sum::<u64>();
 
-    let status_text = format!(
-        "Total: {:.2} GiB, Selected: {:.2} GiB",
-        total_gib_size, selected_gib_size
+    let mut status_text = format!(
+        "Total: {}, Selected: {}",
+        app.size_unit.format(total_size),
+        app.size_unit.format(selected_size)
     );
+    if let Some(goal) = app.goal {
+        // Counted like the plan, with what the clean scope frees of the chosen projects
+        let goal_size = items
+            .iter()
+            .filter(|it| app.selected_items.contains(&it.id))
+            .map(|it| app.goal_sizes.get(&it.id).copied().unwrap_or(it.size))
+            .sum::<u64>();
+        status_text.push_str(&format!(", Goal: {}", app.size_unit.format(goal)));
+        if goal_size < goal {
+            status_text.push_str(&format!(
+                " (short by {})",
+                app.size_unit.format(goal - goal_size)
+            ));
+        } else {
+            status_text.push_str(" (reached)");
+        }
+    }
+    status_text.push_str(&format!(
+        ", Sort: {} ({SORT_COMMAND_KEY}), Scope: {} ({SCOPE_COMMAND_KEY})",
+        app.sort,
+        app.clean_scope.as_str()
+    ));
+    // Scopes removing single build units know upfront how much they free
+    let measured = |size: fn(&UnitSizes) -> u64| {
+        items
+            .iter()
+            .filter_map(|it| it.unit_sizes.as_ref())
+            .map(size)
+            .sum::<u64>()
+    };
+    let reclaimable = match app.clean_scope {
+        CleanScope::StaleToolchains => Some(measured(|it| it.stale_toolchain)),
+        CleanScope::DedupeDeps => Some(measured(|it| it.duplicate)),
+        CleanScope::Check => Some(measured(|it| it.check)),
+        _ => None,
+    };
+    if let Some(reclaimable) = reclaimable {
+        status_text.push_str(&format!(
+            ", Reclaimable: {}",
+            app.size_unit.format(reclaimable)
+        ));
+        // The build units are measured in the background once the scan has finished
+        if items.iter().any(|it| it.unit_sizes.is_none()) {
+            status_text.push_str(" (measuring)");
+        }
+    }
+    if let Some(search) = &app.search {
+        status_text.push_str(&format!(", Search: {search} ({SEARCH_COMMAND_KEY})"));
+    }
+    // Only count what `a` brings back, not projects the search leaves out
+    let now = SystemTime::now();
+    let hidden = app
+        .items
+        .read()
+        .iter()
+        .filter(|it| !app.filter.matches(it, now) && app.matches_search(it, now))
+        .count();
+    if app.show_all {
+        status_text.push_str(&format!(", All projects ({SHOW_ALL_COMMAND_KEY})"));
+    } else if hidden > 0 {
+        status_text.push_str(&format!(", Hidden: {hidden} ({SHOW_ALL_COMMAND_KEY})"));
+    }
+    if let [file_system] = file_systems {
+        status_text.push_str(&format!(
+            ", {}",
+            free_space_text(file_system, app.size_unit)
+        ));
+    }
     let text = Span::styled(status_text, Style::default().fg(Color::Green));
     let block = Block::default();
     let paragraph = Paragraph::new(text).block(block);
     f.render_widget(paragraph, rects[0]);
 
+    if file_systems.len() > 1 {
+        let text = file_systems
+            .iter()
+            .map(|it| {
+                Line::styled(
+                    format!(
+                        "  {}: Selected: {}, {}",
+                        it.usage.mount_point.display(),
+                        app.size_unit.format(it.selected),
+                        free_space_text(it, app.size_unit)
+                    ),
+                    Style::default().fg(Color::Green),
+                )
+            })
+            .collect_vec();
+        f.render_widget(Paragraph::new(text), lines[1]);
+    }
+
     let help_text = Span::styled("h: help", Style::default().fg(Color::Green));
     let block = Block::default();
     let paragraph = Paragraph::new(help_text).block(block);
@@ -419,6 +1472,21 @@ pub fn status_bar(f: &mut Frame, app: &mut App, rect: Rect) {
     f.render_widget(paragraph, rects[2]);
 }
 
+fn free_space_text(file_system: &FileSystemSummary, size_unit: SizeUnit) -> String {
+    let mut text = format!(
+        "Free: {} / {}",
+        size_unit.format(file_system.usage.free),
+        size_unit.format(file_system.usage.total)
+    );
+    if file_system.selected > 0 {
+        text.push_str(&format!(
+            " -> {} after delete",
+            size_unit.format(file_system.projected_free())
+        ));
+    }
+    text
+}
+
 fn sized_centered_rect(min_width: u16, min_height: u16, r: Rect) -> Rect {
     let margin_side = (r.width - min_width) / 2;
     let width = r.width - margin_side * 2;
@@ -443,7 +1511,7 @@ fn sized_centered_rect(min_width: u16, min_height: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .spacing(0)
@@ -464,17 +1532,19 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn progress_percent(progress: &Progress) -> u16 {
-    let total = progress.total;
-    let scanned = progress.scanned;
+pub(crate) fn progress_percent(progress: &Progress) -> u16 {
+    percent(progress.scanned, progress.total)
+}
+
+pub(crate) fn percent(done: usize, total: usize) -> u16 {
     if total == 0 {
         0
     } else {
-        (scanned as f64 / total as f64 * 100.0) as u16
+        (done as f64 / total as f64 * 100.0) as u16
     }
 }
 
-fn progress_text(progress: &Progress) -> String {
+pub(crate) fn progress_text(progress: &Progress) -> String {
     if progress.scanned == progress.total {
         "Finished".to_string()
     } else {
@@ -482,8 +1552,13 @@ fn progress_text(progress: &Progress) -> String {
     }
 }
 
-fn delete_progress_text(progress: &Progress, dry_run: bool) -> String {
-    if progress.scanned == progress.total {
+pub(crate) fn delete_progress_text(progress: &DeleteProgress, dry_run: bool) -> String {
+    if progress.scanned == progress.total && progress.failed > 0 {
+        format!(
+            "Finished ({} failed) Please Push '{}'",
+            progress.failed, DELETE_COMMAND_KEY
+        )
+    } else if progress.scanned == progress.total {
         format!("Finished Please Push '{}'", DELETE_COMMAND_KEY)
     } else {
         format!(
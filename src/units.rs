@@ -0,0 +1,287 @@
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::GIB_SIZE;
+
+/// Size of one kibibyte (KiB) in bytes
+pub const KIB_SIZE: u64 = 1024;
+/// Size of one mebibyte (MiB) in bytes
+pub const MIB_SIZE: u64 = 1024 * 1024;
+
+/// Unit used to display byte sizes in the TUI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnit {
+    /// Pick the largest unit that keeps the value above 1
+    Auto,
+    Kib,
+    Mib,
+    #[default]
+    Gib,
+}
+
+impl SizeUnit {
+    /// Label used in table headers, e.g. `GiB`
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeUnit::Auto => "auto",
+            SizeUnit::Kib => "KiB",
+            SizeUnit::Mib => "MiB",
+            SizeUnit::Gib => "GiB",
+        }
+    }
+
+    /// Format `bytes` in this unit with two decimals, e.g. `1.00 GiB`
+    pub fn format(self, bytes: u64) -> String {
+        let unit = match self {
+            SizeUnit::Auto if bytes >= GIB_SIZE => SizeUnit::Gib,
+            SizeUnit::Auto if bytes >= MIB_SIZE => SizeUnit::Mib,
+            SizeUnit::Auto => SizeUnit::Kib,
+            it => it,
+        };
+        let divisor = match unit {
+            SizeUnit::Kib => KIB_SIZE,
+            SizeUnit::Mib => MIB_SIZE,
+            _ => GIB_SIZE,
+        };
+        format!("{:.2} {}", bytes as f64 / divisor as f64, unit.label())
+    }
+}
+
+/// Parse a byte size like `500MiB`, `1.5G` or `4096`.
+///
+/// Binary suffixes (`K`, `KiB`, `M`, `MiB`, ...) are powers of 1024, SI suffixes (`KB`, `MB`, ...)
+/// powers of 1000 and a bare number is a count of bytes.
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let (number, suffix) = split_number(s)?;
+    let multiplier: u64 = match suffix.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => KIB_SIZE,
+        "m" | "mib" => MIB_SIZE,
+        "g" | "gib" => GIB_SIZE,
+        "t" | "tib" => GIB_SIZE * 1024,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => bail!("unknown size unit `{suffix}` in `{s}`"),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration like `30d`, `12h` or `2w`.
+///
+/// Supported units are `s`, `m` (minutes), `h`, `d`, `w` and `y` (365 days).
+pub fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let (number, suffix) = split_number(s)?;
+    let seconds: u64 = match suffix {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        "" => bail!("missing unit in duration `{s}` (e.g. 30d)"),
+        _ => bail!("unknown duration unit `{suffix}` in `{s}`"),
+    };
+    Duration::try_from_secs_f64(number * seconds as f64)
+        .map_err(|_| anyhow!("duration `{s}` is too long"))
+}
+
+/// A byte size written like `50GiB` in config files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ByteSize(pub u64);
+
+impl TryFrom<String> for ByteSize {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_size(&value).map(Self)
+    }
+}
+
+impl From<ByteSize> for String {
+    fn from(value: ByteSize) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ByteSize {
+    /// Uses the largest binary unit the size is a whole multiple of
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = [
+            ("TiB", GIB_SIZE * 1024),
+            ("GiB", GIB_SIZE),
+            ("MiB", MIB_SIZE),
+            ("KiB", KIB_SIZE),
+        ]
+        .into_iter()
+        .find(|(_, size)| self.0 >= *size && self.0.is_multiple_of(*size));
+        match unit {
+            Some((suffix, size)) => write!(f, "{}{suffix}", self.0 / size),
+            None => write!(f, "{}B", self.0),
+        }
+    }
+}
+
+/// A duration written like `30d` in config files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HumanDuration(pub Duration);
+
+impl TryFrom<String> for HumanDuration {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_duration(&value).map(Self)
+    }
+}
+
+impl From<HumanDuration> for String {
+    fn from(value: HumanDuration) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    /// Uses the largest unit the duration is a whole multiple of
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (suffix, size) = [
+            ("y", 365 * 24 * 60 * 60),
+            ("w", 7 * 24 * 60 * 60),
+            ("d", 24 * 60 * 60),
+            ("h", 60 * 60),
+            ("m", 60),
+        ]
+        .into_iter()
+        .find(|(_, size)| secs >= *size && secs.is_multiple_of(*size))
+        .unwrap_or(("s", 1));
+        write!(f, "{}{suffix}", secs / size)
+    }
+}
+
+fn split_number(s: &str) -> anyhow::Result<(f64, &str)> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let number = s[..split]
+        .parse::<f64>()
+        .map_err(|_| anyhow!("`{s}` does not start with a number"))?;
+    Ok((number, s[split..].trim()))
+}
+
+/// Format a timestamp as RFC 3339 in UTC, e.g. `2024-05-01T12:34:56Z`
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// How long ago something happened in the largest fitting unit, e.g. `3 months ago`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (count, unit) = [
+        (365 * 86_400, "year"),
+        (30 * 86_400, "month"),
+        (7 * 86_400, "week"),
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find(|(size, _)| secs >= *size)
+    .map_or((0, ""), |(size, unit)| (secs / size, unit));
+    match count {
+        0 => "just now".to_string(),
+        1 => format!("1 {unit} ago"),
+        n => format!("{n} {unit}s ago"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("500MiB").unwrap(), 500 * MIB_SIZE);
+        assert_eq!(parse_size("1.5G").unwrap(), GIB_SIZE + GIB_SIZE / 2);
+        assert_eq!(parse_size("2kb").unwrap(), 2_000);
+        assert!(parse_size("12 parsecs").is_err());
+        assert!(parse_size("GiB").is_err());
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(
+            parse_duration("30d").unwrap(),
+            Duration::from_secs(30 * 86_400)
+        );
+        assert_eq!(parse_duration("12h").unwrap(), Duration::from_secs(43_200));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("100000000000000y").is_err());
+    }
+
+    #[test]
+    fn ages_are_rounded_down() {
+        assert_eq!(format_age(Duration::from_secs(59)), "just now");
+        assert_eq!(format_age(Duration::from_secs(3_600)), "1 hour ago");
+        assert_eq!(
+            format_age(Duration::from_secs(100 * 86_400)),
+            "3 months ago"
+        );
+        assert_eq!(format_age(Duration::from_secs(800 * 86_400)), "2 years ago");
+    }
+
+    #[test]
+    fn config_values_round_trip() {
+        assert_eq!(ByteSize(50 * GIB_SIZE).to_string(), "50GiB");
+        assert_eq!(ByteSize(1_500).to_string(), "1500B");
+        assert_eq!(
+            HumanDuration(Duration::from_secs(14 * 86_400)).to_string(),
+            "2w"
+        );
+        assert_eq!(HumanDuration(Duration::from_secs(90)).to_string(), "90s");
+        let size = ByteSize::try_from("1536MiB".to_string()).unwrap();
+        assert_eq!(size.to_string(), "1536MiB");
+    }
+
+    #[test]
+    fn format_timestamps() {
+        assert_eq!(
+            format_timestamp(SystemTime::UNIX_EPOCH),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            format_timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+/// Branch checked out in the git repository containing `project_path`, or the abbreviated
+/// commit when the `HEAD` is detached
+pub fn git_branch(project_path: &Path) -> Option<String> {
+    let git_dir = project_path
+        .ancestors()
+        .find_map(|dir| git_dir(&dir.join(".git")))?;
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.get(..7)?.to_string()),
+    }
+}
+
+/// The git directory of `.git`, which worktrees and submodules point to with a `gitdir:` file
+fn git_dir(dot_git: &Path) -> Option<PathBuf> {
+    if dot_git.is_dir() {
+        return Some(dot_git.to_owned());
+    }
+    let text = std::fs::read_to_string(dot_git).ok()?;
+    let path = text.trim().strip_prefix("gitdir: ")?;
+    Some(dot_git.parent()?.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn branches_of_repositories_and_worktrees() {
+        let root = TempDir::new("vcs");
+        let project = root.join("repo/crates/a");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(root.join("repo/.git/worktrees/wt")).unwrap();
+        std::fs::write(root.join("repo/.git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::create_dir_all(root.join("wt")).unwrap();
+        std::fs::write(
+            root.join("wt/.git"),
+            format!(
+                "gitdir: {}\n",
+                root.join("repo/.git/worktrees/wt").display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("repo/.git/worktrees/wt/HEAD"),
+            "0123456789abcdef0123456789abcdef01234567\n",
+        )
+        .unwrap();
+
+        assert_eq!(git_branch(&project).as_deref(), Some("main"));
+        assert_eq!(git_branch(&root.join("wt")).as_deref(), Some("0123456"));
+    }
+}
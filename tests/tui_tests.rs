@@ -1,14 +1,19 @@
 use cargo_cleaner::{
+    cache_tab::CacheTab,
+    cargo_home::{CacheKind, CacheRemoval},
+    clean::CleanScope,
+    columns::Column,
     notify_rw_lock::NotifyRwLock,
     tui_app::{after_move, ui, App, CursorMode, DeleteState},
-    Progress, ProjectTargetAnalysis, GIB_SIZE,
+    Progress, ProjectTargetAnalysis, UnitSizes, GIB_SIZE,
 };
+use clap::ValueEnum;
 use crossterm::event::KeyCode;
 use itertools::Itertools;
 use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
 use std::sync::mpsc::sync_channel;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
 fn make_project_target(
@@ -17,11 +22,22 @@ fn make_project_target(
     selected_for_cleanup: bool,
     path: Option<String>,
 ) -> ProjectTargetAnalysis {
+    let project_path = std::path::PathBuf::from(path.unwrap_or_else(|| "/test/path".to_string()));
     ProjectTargetAnalysis {
-        project_path: std::path::PathBuf::from(path.unwrap_or_else(|| "/test/path".to_string())),
+        target_path: project_path.join("target"),
+        build_dir: None,
+        project_path,
         project_name: Some(name.to_string()),
         size,
+        allocated_size: 0,
         selected_for_cleanup,
+        built_with: None,
+        compiler_installed: None,
+        unit_sizes: None,
+        orphaned: false,
+        git_branch: None,
+        toolchain: None,
+        workspace_members: None,
         last_modified: SystemTime::now(),
         id: Uuid::new_v4(),
     }
@@ -387,6 +403,46 @@ fn test_status_bar() {
     assert!(content.contains("Unselect"));
 }
 
+/// Test that the status bar shows the free space of the filesystem holding the projects
+#[cfg(unix)]
+#[test]
+fn test_status_bar_free_space() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+
+    // Paths that do not exist are measured on their nearest existing ancestor, i.e. `/`
+    let item = make_project_target("test-project", GIB_SIZE, false, None);
+    let item_id = item.id;
+    app.items.write().push(item);
+
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Free:"));
+    assert!(!content.contains("after delete"));
+
+    app.selected_items.insert(item_id);
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("after delete"));
+}
+
 /// Test clean operation in dry-run mode
 #[test]
 fn test_clean_operation() {
@@ -491,6 +547,779 @@ fn test_clean_operation() {
     assert!(app.selected_items.is_empty());
 }
 
+/// Test that the free-space goal prompt preselects projects and reports the goal
+#[test]
+fn test_free_space_goal() {
+    let backend = TestBackend::new(120, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+
+    let (stale_id, fresh_id) = {
+        let mut items = app.items.write();
+        let mut stale = make_project_target("stale", 2 * GIB_SIZE, false, None);
+        stale.last_modified = SystemTime::now() - Duration::from_secs(90 * 24 * 60 * 60);
+        let fresh = make_project_target("fresh", 2 * GIB_SIZE, false, None);
+        let ids = (stale.id, fresh.id);
+        items.push(fresh);
+        items.push(stale);
+        ids
+    };
+
+    // Open the prompt and enter the goal
+    app.handle_key(KeyCode::Char('f'));
+    assert!(app.goal_input.is_some());
+    for c in "1GiB".chars() {
+        app.handle_key(KeyCode::Char(c));
+    }
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Free how much?"));
+    assert!(content.contains("= 1.00 GiB"));
+
+    // Confirming selects only the stale project, typed keys did not trigger other commands
+    app.handle_key(KeyCode::Enter);
+    assert!(app.goal_input.is_none());
+    assert!(app.delete_state.is_none());
+    assert!(app.selected_items.contains(&stale_id));
+    assert!(!app.selected_items.contains(&fresh_id));
+
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Goal: 1.00 GiB (reached)"));
+
+    // A scope removing only some build units plans with what it frees
+    for item in app.items.write().iter_mut() {
+        let duplicate = if item.id == stale_id { 512 } else { 1024 };
+        item.unit_sizes = Some(UnitSizes {
+            duplicate: duplicate * 1024 * 1024,
+            ..Default::default()
+        });
+    }
+    app.clean_scope = CleanScope::DedupeDeps;
+    app.apply_goal(GIB_SIZE);
+    assert_eq!(app.selected_items, [fresh_id].into_iter().collect());
+    // Both together free 1.5 GiB of their 4 GiB
+    app.apply_goal(2 * GIB_SIZE);
+    assert_eq!(app.selected_items.len(), 2);
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Selected: 4.00 GiB"));
+    assert!(content.contains("Goal: 2.00 GiB (short by 0.50 GiB)"));
+
+    // The prompt fits into terminals narrower than itself
+    let mut narrow = Terminal::new(TestBackend::new(30, 30)).unwrap();
+    app.handle_key(KeyCode::Char('f'));
+    narrow.draw(|frame| ui(frame, &mut app)).unwrap();
+}
+
+/// Test that the clean scope chooser switches the scope used by the delete window
+#[test]
+fn test_clean_scope_chooser() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    app.items
+        .write()
+        .push(make_project_target("project", GIB_SIZE, false, None));
+
+    app.handle_key(KeyCode::Char('c'));
+    assert_eq!(app.scope_chooser, Some(0));
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Clean scope (Enter/Esc)"));
+    assert!(content.contains("sweep"));
+
+    // Keys move inside the chooser instead of the project list
+    app.handle_key(KeyCode::Char('G'));
+    let sweep = CleanScope::value_variants()
+        .iter()
+        .position(|it| *it == CleanScope::Sweep)
+        .unwrap();
+    for _ in 0..sweep {
+        app.handle_key(KeyCode::Char('j'));
+    }
+    app.handle_key(KeyCode::Enter);
+    assert!(app.scope_chooser.is_none());
+    assert_eq!(app.clean_scope, CleanScope::Sweep);
+    assert_eq!(app.table_state.selected(), None);
+
+    app.handle_key(KeyCode::Char('j'));
+    app.handle_key(KeyCode::Char(' '));
+    app.handle_key(KeyCode::Char('d'));
+    terminal
+        .draw(|frame| {
+            ui(frame, &mut app);
+        })
+        .unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Scope: sweep (c)"));
+    assert!(content.contains("artifacts unused for 30d"));
+
+    // Scopes removing single build units report what they free once it is measured
+    app.delete_state = None;
+    app.clean_scope = CleanScope::DedupeDeps;
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Reclaimable: 0.00 GiB (measuring)"));
+    app.items.write()[0].unit_sizes = Some(UnitSizes {
+        duplicate: GIB_SIZE,
+        ..Default::default()
+    });
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Reclaimable: 1.00 GiB,"));
+}
+
 fn buffer_content_to_string(buffer: &Buffer) -> String {
     buffer.content().iter().map(|cell| cell.symbol()).join("")
 }
+
+/// Test that the registry tab lists crate versions and removes the chosen parts
+#[test]
+fn test_registry_tab() {
+    let backend = TestBackend::new(120, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx.clone(), scan_progress);
+
+    let cargo_home = std::env::temp_dir().join(format!("cargo-cleaner-tui-{}", Uuid::new_v4()));
+    let group = cargo_home
+        .join("registry")
+        .join("src")
+        .join("index.crates.io-0");
+    std::fs::create_dir_all(group.join("serde-1.0.217")).unwrap();
+    std::fs::write(group.join("serde-1.0.217").join("lib.rs"), [0; 2048]).unwrap();
+    app.tabs
+        .push(CacheTab::new(CacheKind::Registry, cargo_home.clone(), tx));
+
+    // The cache is only analyzed once its tab is shown
+    assert!(app.tabs[0].scan_progress.is_none());
+    app.handle_key(KeyCode::Tab);
+    assert_eq!(app.tab, 1);
+    wait_until(|| app.tabs[0].items.read().len() == 1);
+
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Projects"));
+    assert!(content.contains("serde"));
+    assert!(content.contains("1.0.217"));
+    assert!(content.contains("Removes: sources"));
+
+    // No scanned project locks serde, `u` selects it once it is old enough
+    app.tabs[0].older_than = Duration::ZERO;
+    *app.scan_finished.write() = true;
+    app.on_async_update();
+    assert_eq!(app.tabs[0].items.read()[0].in_use, Some(false));
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Unused: 0.00 GiB (u)"));
+    app.handle_key(KeyCode::Char('u'));
+    assert_eq!(app.tabs[0].selected_items.len(), 1);
+
+    app.handle_key(KeyCode::Char('d'));
+    app.handle_key(KeyCode::Char('Y'));
+    wait_until(|| {
+        app.handle_key(KeyCode::Char('d'));
+        app.tabs[0].delete_state.is_none()
+    });
+    assert!(app.tabs[0].items.read().is_empty());
+    // Nothing is removed from the disk in dry-run mode
+    assert!(group.join("serde-1.0.217").exists());
+
+    app.handle_key(KeyCode::BackTab);
+    assert_eq!(app.tab, 0);
+    std::fs::remove_dir_all(&cargo_home).unwrap();
+}
+
+/// Test that the toolchain rustup uses by default can not be selected nor removed
+#[test]
+fn test_protected_toolchain() {
+    let backend = TestBackend::new(120, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx.clone(), scan_progress);
+
+    let rustup_home = std::env::temp_dir().join(format!("cargo-cleaner-tui-{}", Uuid::new_v4()));
+    for name in [
+        "stable-x86_64-unknown-linux-gnu",
+        "1.60.0-x86_64-unknown-linux-gnu",
+    ] {
+        let dir = rustup_home.join("toolchains").join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rustc"), [0; 2048]).unwrap();
+    }
+    std::fs::write(
+        rustup_home.join("settings.toml"),
+        "default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n",
+    )
+    .unwrap();
+    app.tabs.push(CacheTab::new(
+        CacheKind::Toolchains,
+        rustup_home.clone(),
+        tx,
+    ));
+    app.handle_key(KeyCode::Tab);
+    wait_until(|| app.tabs[0].is_scanned());
+    let row = |channel: &str| {
+        app.tabs[0]
+            .items
+            .read()
+            .iter()
+            .position(|it| it.name == channel)
+            .unwrap()
+    };
+    let (stable, old) = (row("stable"), row("1.60.0"));
+
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("kept"));
+
+    app.tabs[0].table_state.select(Some(stable));
+    app.handle_key(KeyCode::Char(' '));
+    assert!(app.tabs[0].selected_items.is_empty());
+    app.tabs[0].table_state.select(Some(old));
+    app.handle_key(KeyCode::Char(' '));
+    assert_eq!(app.tabs[0].selected_items.len(), 1);
+
+    // Removing it anyway is refused
+    let entry = app.tabs[0].items.read()[stable].clone();
+    assert!(CacheKind::Toolchains
+        .remove(&rustup_home, &entry, CacheRemoval::All)
+        .is_err());
+    assert!(entry.parts[0].path.exists());
+    std::fs::remove_dir_all(&rustup_home).unwrap();
+}
+
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    for _ in 0..500 {
+        if condition() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("condition not reached in time");
+}
+
+/// Test that Enter drills down into the sizes per crate of the project under the cursor
+#[test]
+fn test_crate_breakdown() {
+    let backend = TestBackend::new(120, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+
+    let project = std::env::temp_dir().join(format!("cargo-cleaner-tui-{}", Uuid::new_v4()));
+    let debug = project.join("target").join("debug");
+    // `aaa-small` was only checked, leaving nothing but metadata
+    for (package, hash, size, extension) in [
+        ("aaa-small", "0000000000000001", 10, "rmeta"),
+        ("zzz-big", "0000000000000002", 4096, "rlib"),
+    ] {
+        std::fs::create_dir_all(debug.join(".fingerprint").join(format!("{package}-{hash}")))
+            .unwrap();
+        std::fs::create_dir_all(debug.join("deps")).unwrap();
+        let file_name = format!("lib{}-{hash}.{extension}", package.replace('-', "_"));
+        std::fs::write(debug.join("deps").join(file_name), vec![0; size]).unwrap();
+    }
+    app.items.write().push(make_project_target(
+        "project",
+        GIB_SIZE,
+        false,
+        Some(project.to_string_lossy().into_owned()),
+    ));
+
+    // Nothing to drill into without a cursor
+    app.handle_key(KeyCode::Enter);
+    assert!(app.breakdown.is_none());
+    app.handle_key(KeyCode::Char('j'));
+    app.handle_key(KeyCode::Enter);
+    wait_until(|| app.breakdown.as_ref().unwrap().items.read().is_some());
+
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Crate"));
+    assert!(content.contains("Build Scripts"));
+    assert!(content.contains("Check Only"));
+    assert!(content.contains("in 2 crates, rust-analyzer: 0.00 GiB"));
+    let check_only_size = app
+        .breakdown
+        .as_ref()
+        .and_then(|it| it.items.read().as_ref().map(|it| it.check_only_size));
+    assert_eq!(check_only_size, Some(10));
+    let names = |app: &App| {
+        app.breakdown
+            .as_ref()
+            .unwrap()
+            .sorted_items()
+            .into_iter()
+            .map(|it| it.name)
+            .collect_vec()
+    };
+    assert_eq!(names(&app), ["zzz-big", "aaa-small"]);
+
+    app.handle_key(KeyCode::Char('s'));
+    assert_eq!(names(&app), ["aaa-small", "zzz-big"]);
+    // Keys move inside the breakdown instead of the project list
+    app.handle_key(KeyCode::Char('j'));
+    assert_eq!(app.table_state.selected(), Some(0));
+
+    app.handle_key(KeyCode::Esc);
+    assert!(app.breakdown.is_none());
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Project Path"));
+    std::fs::remove_dir_all(&project).unwrap();
+}
+
+/// Test that projects without build output are only listed when showing all projects
+#[test]
+fn test_show_all_projects() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    {
+        let mut items = app.items.write();
+        items.push(make_project_target(
+            "never-built",
+            0,
+            false,
+            Some("/test/never-built".to_string()),
+        ));
+        items.push(make_project_target(
+            "built",
+            GIB_SIZE,
+            false,
+            Some("/test/built".to_string()),
+        ));
+    }
+    app.table_state.select(Some(0));
+
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(!content.contains("never-built"));
+    assert!(content.contains("Hidden: 1 (a)"));
+
+    // The cursor stays on the project it was on
+    app.handle_key(KeyCode::Char('a'));
+    assert!(app.show_all);
+    assert_eq!(app.table_state.selected(), Some(1));
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("never-built"));
+    assert!(content.contains("All projects (a)"));
+
+    app.handle_key(KeyCode::Char('g'));
+    app.handle_key(KeyCode::Char(' '));
+    assert_eq!(app.selected_items.len(), 1);
+    assert!(app.selected_items.contains(&app.items.read()[0].id));
+
+    // A minimum size hides small targets as well
+    app.handle_key(KeyCode::Char('a'));
+    app.filter.min_size = Some(2 * GIB_SIZE);
+    assert!(app.shown_items().is_empty());
+}
+
+/// Test that the project list stays sorted and the cursor follows its project
+#[test]
+fn test_sort_projects() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    let names = |app: &App| {
+        app.shown_items()
+            .iter()
+            .map(|it| it.project_name.clone().unwrap())
+            .collect_vec()
+    };
+    let cursor = |app: &App| {
+        let index = app.table_state.selected().unwrap();
+        app.shown_items()[index].project_name.clone().unwrap()
+    };
+    for (name, size) in [("b", 1), ("a", 3), ("c", 2)] {
+        app.items.write().push(make_project_target(
+            name,
+            size * GIB_SIZE,
+            false,
+            Some(format!("/test/{name}")),
+        ));
+    }
+    app.handle_key(KeyCode::Char('j'));
+    assert_eq!(cursor(&app), "b");
+
+    // Streamed in results are sorted by size, largest first
+    app.on_async_update();
+    assert_eq!(names(&app), ["a", "c", "b"]);
+    assert_eq!(cursor(&app), "b");
+
+    // `s` moves on to the next shown column, wrapping around
+    app.columns = vec![Column::Path, Column::Name, Column::Size];
+    app.handle_key(KeyCode::Char('s'));
+    assert_eq!(app.sort.to_string(), "path:asc");
+    assert_eq!(names(&app), ["a", "b", "c"]);
+    assert_eq!(cursor(&app), "b");
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Project Path ▲"));
+
+    app.handle_key(KeyCode::Char('S'));
+    assert_eq!(names(&app), ["c", "b", "a"]);
+    assert_eq!(cursor(&app), "b");
+
+    app.items.write().push(make_project_target(
+        "d",
+        0,
+        false,
+        Some("/test/d".to_string()),
+    ));
+    app.handle_key(KeyCode::Char('a'));
+    app.on_async_update();
+    assert_eq!(names(&app), ["d", "c", "b", "a"]);
+    assert_eq!(cursor(&app), "b");
+}
+
+/// Test that the column chooser adds and removes table columns
+#[test]
+fn test_column_chooser() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    // The branch is only read once its column is shown
+    let root = std::env::temp_dir().join(format!("cargo-cleaner-tui-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::write(root.join(".git/HEAD"), "ref: refs/heads/feature/columns\n").unwrap();
+    let mut project = make_project_target(
+        "branched",
+        GIB_SIZE,
+        false,
+        Some(root.to_string_lossy().into_owned()),
+    );
+    project.last_modified = SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60);
+    app.items.write().push(project);
+
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("3 days ago"));
+    assert!(!content.contains("feature/columns"));
+    assert_eq!(app.items.read()[0].git_branch, None);
+
+    app.handle_key(KeyCode::Char('C'));
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("[x] path"));
+    assert!(content.contains("[ ] git-branch"));
+
+    // Hide the path and show the branch after the other columns
+    app.handle_key(KeyCode::Char(' '));
+    for _ in 0..6 {
+        app.handle_key(KeyCode::Char('j'));
+    }
+    app.handle_key(KeyCode::Char(' '));
+    app.handle_key(KeyCode::Enter);
+    assert_eq!(app.column_chooser, None);
+    assert_eq!(
+        app.columns,
+        [
+            Column::Name,
+            Column::Size,
+            Column::Age,
+            Column::BuiltWith,
+            Column::GitBranch
+        ]
+    );
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Git Branch"));
+    assert!(content.contains("feature/columns"));
+    assert!(!content.contains("Project Path"));
+    std::fs::remove_dir_all(&root).unwrap();
+
+    // The last column stays
+    app.columns = vec![Column::Size];
+    app.column_chooser = Some(2);
+    app.handle_key(KeyCode::Char(' '));
+    assert_eq!(app.columns, [Column::Size]);
+}
+
+/// Test that the search narrows the project list and selecting applies to the listed projects
+#[test]
+fn test_search() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    for (name, size, path) in [
+        ("api", 2, "/work/clientA/api"),
+        ("web", 1, "/work/clientA/web"),
+        ("api", 3, "/work/clientB/api"),
+    ] {
+        app.items.write().push(make_project_target(
+            name,
+            size * GIB_SIZE,
+            false,
+            Some(path.to_string()),
+        ));
+    }
+    app.on_async_update();
+    let paths = |app: &App| {
+        app.shown_items()
+            .iter()
+            .map(|it| it.project_path.to_string_lossy().into_owned())
+            .collect_vec()
+    };
+    let type_text = |app: &mut App, text: &str| {
+        for c in text.chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+    };
+
+    // The list follows the query while it is typed
+    app.handle_key(KeyCode::Char('/'));
+    type_text(&mut app, "cliA");
+    assert_eq!(paths(&app), ["/work/clientA/api", "/work/clientA/web"]);
+    // An incomplete condition leaves the list as it was
+    type_text(&mut app, " size");
+    let before = paths(&app);
+    type_text(&mut app, ">");
+    assert_eq!(paths(&app), before);
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("invalid: missing value in `size>`"));
+    app.handle_key(KeyCode::Enter);
+    assert!(app.search_input.is_some());
+
+    type_text(&mut app, "1500MiB");
+    app.handle_key(KeyCode::Enter);
+    assert_eq!(app.search_input, None);
+    assert_eq!(paths(&app), ["/work/clientA/api"]);
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Search: cliA size>1500MiB (/)"));
+
+    // Selecting all picks the listed projects only, a second time unselects them
+    app.handle_key(KeyCode::Char('A'));
+    let listed = app.shown_items()[0].id;
+    assert_eq!(app.selected_items, [listed].into_iter().collect());
+    app.handle_key(KeyCode::Char('A'));
+    assert!(app.selected_items.is_empty());
+
+    // Editing the query starts from the current one
+    app.handle_key(KeyCode::Char('/'));
+    assert_eq!(app.search_input.as_deref(), Some("cliA size>1500MiB"));
+    for _ in 0.."cliA size>1500MiB".len() {
+        app.handle_key(KeyCode::Backspace);
+    }
+    type_text(&mut app, "api");
+    app.handle_key(KeyCode::Enter);
+    assert_eq!(paths(&app), ["/work/clientB/api", "/work/clientA/api"]);
+
+    app.table_state.select(Some(0));
+    app.handle_key(KeyCode::Char('n'));
+    assert_eq!(app.table_state.selected(), Some(1));
+    app.handle_key(KeyCode::Char('n'));
+    assert_eq!(app.table_state.selected(), Some(0));
+    app.handle_key(KeyCode::Char('N'));
+    assert_eq!(app.table_state.selected(), Some(1));
+
+    // Escape in the prompt clears the search
+    app.handle_key(KeyCode::Char('/'));
+    app.handle_key(KeyCode::Esc);
+    assert_eq!(app.search, None);
+    assert_eq!(paths(&app).len(), 3);
+    assert_eq!(
+        app.shown_items()[app.table_state.selected().unwrap()]
+            .project_path
+            .to_string_lossy(),
+        "/work/clientA/api"
+    );
+
+    // Only the listed projects of the selection are cleaned
+    app.handle_key(KeyCode::Char('/'));
+    type_text(&mut app, "api");
+    app.handle_key(KeyCode::Enter);
+    let web = app
+        .items
+        .read()
+        .iter()
+        .find(|it| it.project_name.as_deref() == Some("web"))
+        .unwrap()
+        .id;
+    app.selected_items.insert(web);
+    app.handle_key(KeyCode::Char('d'));
+    assert!(app.delete_state.is_none());
+    app.handle_key(KeyCode::Char('A'));
+    // `n` cancels the confirmation instead of jumping
+    let cursor = app.table_state.selected();
+    app.handle_key(KeyCode::Char('d'));
+    app.handle_key(KeyCode::Char('n'));
+    assert!(app.delete_state.is_none());
+    assert_eq!(app.table_state.selected(), cursor);
+    app.handle_key(KeyCode::Char('d'));
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("Selected: 5.00 GiB"));
+    assert!(content.contains("1 selected but hidden crates are kept"));
+    app.handle_key(KeyCode::Char('Y'));
+    assert_eq!(app.deleting_items.len(), 2);
+    assert!(!app.deleting_items.contains(&web));
+    if let Some(DeleteState::Deleting(progress)) = &app.delete_state {
+        let mut progress = progress.write();
+        progress.scanned = progress.total;
+    }
+    app.handle_key(KeyCode::Char('d'));
+    assert!(app.delete_state.is_none());
+    // The hidden project is still selected, the cleaned ones are gone in a dry-run
+    assert_eq!(app.selected_items, [web].into_iter().collect());
+    assert_eq!(app.items.read().len(), 1);
+}
+
+/// Test that the tree view groups projects by directory and selects whole subtrees
+#[test]
+fn test_tree_view() {
+    let backend = TestBackend::new(160, 30);
+    let mut terminal = Terminal::new(backend).unwrap();
+    let (tx, _rx) = sync_channel(1);
+    let scan_progress = Arc::new(NotifyRwLock::new(
+        tx.clone(),
+        Progress {
+            total: 0,
+            scanned: 0,
+        },
+    ));
+    let mut app = App::new(true, tx, scan_progress);
+    for (name, size, path) in [
+        ("api", 2, "/work/clientA/api"),
+        ("web", 1, "/work/clientA/web"),
+        ("shop", 3, "/work/clientB/shop"),
+    ] {
+        app.items.write().push(make_project_target(
+            name,
+            size * GIB_SIZE,
+            false,
+            Some(path.to_string()),
+        ));
+    }
+    app.on_async_update();
+    let ids = app.shown_items().iter().map(|it| it.id).collect_vec();
+
+    app.handle_key(KeyCode::Char('t'));
+    assert_eq!(app.rows().len(), 5);
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("▾ /work/"));
+    assert!(content.contains("3 projects"));
+    assert!(content.contains("6.00 GiB"));
+    assert!(content.contains("  clientB/shop"));
+    assert!(content.contains("▾ clientA/"));
+    assert!(content.contains("    api"));
+
+    // Selecting a directory selects every project below it
+    app.table_state.select(Some(2));
+    app.handle_key(KeyCode::Char(' '));
+    assert_eq!(app.selected_items, [ids[1], ids[2]].into_iter().collect());
+
+    app.handle_key(KeyCode::Left);
+    assert_eq!(app.rows().len(), 3);
+    assert_eq!(app.table_state.selected(), Some(2));
+    terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    let content = buffer_content_to_string(terminal.backend().buffer());
+    assert!(content.contains("▸ clientA/"));
+    assert!(!content.contains("    api"));
+
+    // Enter expands it again, left on a project moves to its directory
+    app.handle_key(KeyCode::Enter);
+    assert_eq!(app.rows().len(), 5);
+    assert!(app.breakdown.is_none());
+    app.handle_key(KeyCode::Char('j'));
+    app.handle_key(KeyCode::Left);
+    assert_eq!(app.table_state.selected(), Some(2));
+
+    // The flat list keeps the cursor on the same project
+    app.handle_key(KeyCode::Char('j'));
+    app.handle_key(KeyCode::Char('t'));
+    assert_eq!(app.rows().len(), 3);
+    assert_eq!(app.table_state.selected(), Some(1));
+}